@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::process;
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use shx_config::config::path_for;
+
+use crate::error::CdxError;
+
+const BOOKMARKS: &str = "cdx.bookmarks.toml";
+
+/// Directories named explicitly with `--mark`, backed by `cdx.bookmarks.toml`.
+///
+/// Unlike the history, bookmarks never age nor get evicted.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Bookmarks {
+    #[serde(default)]
+    marks: BTreeMap<String, Bookmark>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub canonical: String,
+    /// The path the directory was marked by, with its symlinks unresolved.
+    pub logical: String,
+}
+
+impl Bookmarks {
+    pub fn open() -> anyhow::Result<Self> {
+        let path = path_for(BOOKMARKS)?;
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .with_context(|| format!("[fatal] cannot parse bookmarks: {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Bookmarks::default()),
+            Err(e) => Err(e).with_context(|| format!("[fatal] cannot read bookmarks: {}", path.display())),
+        }
+        .map_err(|e| CdxError::Database(e).into())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Bookmark> {
+        self.marks.get(name)
+    }
+
+    /// Returns all bookmarks, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Bookmark)> {
+        self.marks.iter()
+    }
+
+    /// Names the directory, replacing whatever the name denoted before.
+    pub fn mark(&mut self, name: String, bookmark: Bookmark) -> anyhow::Result<()> {
+        if name.is_empty() || name.contains(|it: char| it.is_whitespace() || it == '/') {
+            bail!("[error] invalid bookmark name `{}`, which must not be empty nor contain spaces or `/`", name);
+        }
+        self.marks.insert(name, bookmark);
+        Ok(())
+    }
+
+    pub fn unmark(&mut self, name: &str) -> Option<Bookmark> {
+        self.marks.remove(name)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        self.write().map_err(|e| CdxError::Database(e).into())
+    }
+
+    // replaces the file atomically, so that a concurrent reader never sees it half written.
+    fn write(&self) -> anyhow::Result<()> {
+        let path = path_for(BOOKMARKS)?;
+        let tmp = path_for(format!("{}.{}.tmp", BOOKMARKS, process::id()))?;
+        fs::write(&tmp, toml::to_string(self)?)
+            .with_context(|| format!("[fatal] cannot write bookmarks: {}", tmp.display()))?;
+        fs::rename(&tmp, &path)
+            .with_context(|| format!("[fatal] cannot replace bookmarks: {}", path.display()))?;
+        Ok(())
+    }
+}
@@ -1,78 +1,392 @@
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::iter;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
-use anyhow::{bail, Context};
-use inquire::Select;
-use shx_config::cdx::CdxConfig;
+use anyhow::bail;
+use inquire::{InquireError, Select};
+use shx_config::cdx::{Ambiguity, CdxConfig, PathMode};
 use shx_config::config::home;
 
+use crate::bookmark::Bookmarks;
+use crate::crawler::Crawler;
+use crate::error::CdxError;
 use crate::history::{Entry, History};
+use crate::matcher;
+use crate::matcher::Matcher;
+use crate::matcher::fuzzy::FuzzyMatcher;
+use crate::matcher::keyword::KeywordMatcher;
+use crate::path;
+use crate::probe::PathState;
+use crate::project::Project;
+use crate::session;
+use crate::session::Session;
 use crate::theme::{SelectTheme, Theme};
-use crate::theme::formatter::ToPretty;
+use crate::theme::formatter::{Query, StyledEntry, ToPretty};
 
 pub struct CD;
 
 impl CD {
-    pub fn builtin(_config: &CdxConfig, history: &mut History, dest: String) -> anyhow::Result<PathBuf> {
-        let path = match dest.as_str() {
+    pub fn builtin(config: &CdxConfig, history: &mut History, session: Option<&Session>, dest: String) -> anyhow::Result<PathBuf> {
+        let target = match dest.as_str() {
             "" => home()?,
-            "-" => Self::revision(_config, history, 2)?,
+            "-" => Self::previous(config, history, session)?,
             _ => PathBuf::from(&dest),
-        }.canonicalize().context("failed to canonicalize path.")?;
-        let row = Entry::new(&dest, &path);
+        };
+        Self::change_to(config, history, target, &dest)
+    }
+
+    pub fn ancestor(config: &CdxConfig, history: &mut History, levels: usize) -> anyhow::Result<PathBuf> {
+        let current = path::logical(Path::new("."));
+        let Some(target) = current.ancestors().nth(levels) else {
+            bail!(CdxError::NoMatch(format!("[error] {} has no ancestor {} levels up", current.display(), levels)));
+        };
+        Self::change_to(config, history, target.to_path_buf(), &format!("..{}", levels))
+    }
+
+    /// Goes up to the nearest ancestor named `name`, or else starting with it.
+    pub fn named_ancestor(config: &CdxConfig, history: &mut History, name: String) -> anyhow::Result<PathBuf> {
+        let current = path::logical(Path::new("."));
+        let named = |exact: bool| current.ancestors()
+            .skip(1)
+            .find(|it| it.file_name().and_then(|it| it.to_str()).is_some_and(|it| match exact {
+                true => it == name,
+                false => it.starts_with(&name),
+            }));
+        let Some(target) = named(true).or_else(|| named(false)) else {
+            bail!(CdxError::NoMatch(format!("[error] no ancestor of {} is named `{}`", current.display(), name)));
+        };
+        Self::change_to(config, history, target.to_path_buf(), &format!(":^{}", name))
+    }
+
+    /// Goes up to the root of the project the current directory belongs to, which may be the current directory.
+    /// It is the same project scoped lookups stay within.
+    pub fn project_root(config: &CdxConfig, history: &mut History) -> anyhow::Result<PathBuf> {
+        let Some(project) = Project::current(&config.project_markers()) else {
+            let current = path::logical(Path::new("."));
+            bail!(CdxError::NoMatch(format!("[error] {} is not within a project", current.display())));
+        };
+        Self::change_to(config, history, project.root().to_path_buf(), ":^")
+    }
+
+    /// Replaces the component `old` of the current directory by `new`, trying the deepest one first.
+    /// Without such a component, replaces the first occurrence of `old` as zsh does.
+    pub fn substitute(config: &CdxConfig, history: &mut History, old: String, new: String) -> anyhow::Result<PathBuf> {
+        let current = path::logical(Path::new("."));
+        let components = current.components().collect::<Vec<_>>();
+        let mut candidates = components.iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, it)| it.as_os_str() == old.as_str())
+            .map(|(index, _)| {
+                let mut replaced = components.iter().map(|it| it.as_os_str().to_os_string()).collect::<Vec<_>>();
+                replaced[index] = new.clone().into();
+                replaced.iter().collect::<PathBuf>()
+            })
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            let current = current.display().to_string();
+            if current.contains(&old) {
+                candidates.push(PathBuf::from(current.replacen(&old, &new, 1)));
+            }
+        }
+
+        let Some(target) = candidates.into_iter().find(|it| it.is_dir()) else {
+            bail!(CdxError::NoMatch(format!("[error] no directory replacing `{}` by `{}` in {}", old, new, current.display())));
+        };
+        Self::change_to(config, history, target, &format!("{} {}", old, new))
+    }
+
+    /// Looks for `name` among the siblings of the current directory, preferring one named exactly so
+    /// to one starting with it, then for a directory named exactly so next to each of its ancestors.
+    pub fn sibling(config: &CdxConfig, history: &mut History, name: String) -> anyhow::Result<PathBuf> {
+        let current = path::logical(Path::new("."));
+        let mut parents = current.ancestors().skip(1);
+        let target = parents.next()
+            .and_then(|parent| Self::child(parent, &name, true).or_else(|| Self::child(parent, &name, false)))
+            .or_else(|| parents.find_map(|it| Self::child(it, &name, true)));
+        let Some(target) = target else {
+            bail!(CdxError::NoMatch(format!("[error] no directory named `{}` next to {} nor its ancestors", name, current.display())));
+        };
+        Self::change_to(config, history, target, &format!(":~{}", name))
+    }
+
+    fn child(parent: &Path, name: &str, exact: bool) -> Option<PathBuf> {
+        if exact {
+            return Some(parent.join(name)).filter(|it| it.is_dir());
+        }
+        let mut children = fs::read_dir(parent).ok()?
+            .filter_map(Result::ok)
+            .filter(|it| it.file_name().to_str().is_some_and(|it| it.starts_with(name)))
+            .map(|it| it.path())
+            .filter(|it| it.is_dir())
+            .collect::<Vec<_>>();
+        children.sort();
+        children.into_iter().next()
+    }
+
+    // changes to a directory given by the user, rather than found in history.
+    fn change_to(config: &CdxConfig, history: &mut History, target: PathBuf, dest: &str) -> anyhow::Result<PathBuf> {
+        let logical = path::logical(&target);
+        // like `cd -L`, `..` goes back over the symlinks first, and through them only if that fails.
+        let path = logical.canonicalize()
+            .or_else(|_| target.canonicalize())
+            .map_err(|e| CdxError::NoMatch(format!("[error] cannot change directory to `{}`: {}", dest, e)))?;
+        let row = Entry::new(dest, &path).with_logical(&logical);
         history.append_last(row);
-        Ok(path)
+        match config.path_mode() {
+            PathMode::Logical => Ok(logical),
+            PathMode::Physical => Ok(path),
+        }
     }
 
-    pub fn shortcut(config: &CdxConfig, history: &mut History, input: String) -> anyhow::Result<PathBuf> {
-        let input_path = PathBuf::from(input.clone());
-        let search_size = config.search_size();
+    /// Jumps to the best-ranked directory matching all keywords, searching the whole history,
+    /// or only the directories within the current project if `scoped`.
+    /// When none matches them as they are, searches the filesystem for one, then matches the history fuzzily.
+    pub fn shortcut(config: &CdxConfig, history: &mut History, keywords: Vec<String>, scoped: bool) -> anyhow::Result<PathBuf> {
+        let input = keywords.join(" ");
+        let raw = format!(":{}{}", if scoped { "=" } else { "" }, input);
+        let project = Self::project(config, scoped)?;
+        let scored = Self::scored(config, history, project.as_ref(), scoped);
+        let mut matched = matcher::rank(&KeywordMatcher::new(&keywords), scored.clone());
+        if matched.is_empty() {
+            let roots = match &project {
+                Some(project) if scoped => vec![project.root().to_path_buf()],
+                _ => Self::search_roots(config),
+            };
+            if let Some(found) = Self::search(config, &keywords, roots) {
+                return Self::change_to(config, history, found, &raw);
+            }
+            matched = matcher::rank(&FuzzyMatcher::new(&keywords.concat()), scored);
+        }
 
-        for entry in history.read(search_size) {
-            let p = PathBuf::from(&entry.canonical);
-            if p.ends_with(&input_path) {
-                let to_append = entry.with_raw(format!(":{}", input));
-                history.append_last(to_append);
-                return Ok(p);
+        if matched.is_empty() {
+            bail!(CdxError::NoMatch(format!("[error] failed to find history by shortcut `{}`", input)));
+        }
+        Self::resolve(config, history, matched, raw)
+    }
+
+    /// The directories a shortcut matches, best first, without searching the filesystem.
+    pub fn candidates(config: &CdxConfig, history: &History, keywords: &[String], scoped: bool) -> anyhow::Result<Vec<(f64, Entry)>> {
+        let project = Self::project(config, scoped)?;
+        let scored = Self::scored(config, history, project.as_ref(), scoped);
+        let matched = matcher::rank(&KeywordMatcher::new(keywords), scored.clone());
+        match matched.is_empty() {
+            true => Ok(matcher::rank(&FuzzyMatcher::new(&keywords.concat()), scored)),
+            false => Ok(matched),
+        }
+    }
+
+    /// The directories a regex or a glob matches, best first.
+    pub fn pattern_candidates(config: &CdxConfig, history: &History, matcher: &dyn Matcher) -> anyhow::Result<Vec<(f64, Entry)>> {
+        let project = Self::project(config, false)?;
+        Ok(matcher::rank(matcher, Self::scored(config, history, project.as_ref(), false)))
+    }
+
+    // the project of the current directory, which a scoped lookup cannot do without.
+    fn project(config: &CdxConfig, scoped: bool) -> anyhow::Result<Option<Project>> {
+        let project = Project::current(&config.project_markers());
+        if project.is_none() && scoped {
+            let current = path::logical(Path::new("."));
+            bail!(CdxError::NoMatch(format!("[error] {} is not within a project", current.display())));
+        }
+        Ok(project)
+    }
+
+    // the whole history ranked by frecency, boosting the directories within the project, or keeping only them if `scoped`.
+    fn scored(config: &CdxConfig, history: &History, project: Option<&Project>, scoped: bool) -> Vec<(f64, Entry)> {
+        let scored = history.scored(usize::MAX);
+        let Some(project) = project else {
+            return scored;
+        };
+        scored.into_iter()
+            .filter_map(|(score, entry)| match project.contains(&entry) {
+                true => Some((score * config.project_boost(), entry)),
+                false => (!scoped).then_some((score, entry)),
+            })
+            .collect()
+    }
+
+    // the current directory, then `search_roots`.
+    fn search_roots(config: &CdxConfig) -> Vec<PathBuf> {
+        let roots = config.search_roots()
+            .into_iter()
+            .filter_map(|it| match it.strip_prefix("~/") {
+                Some(rest) => home().ok().map(|home| home.join(rest)),
+                None => Some(PathBuf::from(it)),
+            });
+        iter::once(path::logical(Path::new("."))).chain(roots).collect()
+    }
+
+    // looks for a directory matching the keywords below each of the roots in turn,
+    // for at most `search_timeout_ms` in all.
+    fn search(config: &CdxConfig, keywords: &[String], roots: Vec<PathBuf>) -> Option<PathBuf> {
+        let matcher = KeywordMatcher::new(keywords);
+        let deadline = Instant::now() + Duration::from_millis(config.search_timeout_ms());
+        let crawler = Crawler::new(config).with_depth(Some(config.search_depth()));
+        roots.iter().find_map(|root| crawler.find(root, &matcher, deadline))
+    }
+
+    /// Jumps to the best-ranked directory matching a regex or a glob, searching the whole history.
+    pub fn pattern(config: &CdxConfig, history: &mut History, matcher: &dyn Matcher, raw: String) -> anyhow::Result<PathBuf> {
+        let matched = Self::pattern_candidates(config, history, matcher)?;
+        if matched.is_empty() {
+            bail!(CdxError::NoMatch(format!("[error] failed to find history by pattern `{}`", raw)));
+        }
+        Self::resolve(config, history, matched, raw)
+    }
+
+    // picks one of the ranked matches as the ambiguity policy of the config says,
+    // skipping those whose directories cannot be found.
+    fn resolve(config: &CdxConfig, history: &mut History, matched: Vec<(f64, Entry)>, raw: String) -> anyhow::Result<PathBuf> {
+        let best = matched[0].1.clone();
+        let mut candidates = matched.into_iter()
+            .map(|(_, entry)| entry)
+            .filter(|it| history.probe(it) == PathState::Present)
+            .take(config.search_size())
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            // fails telling why the best match cannot be changed to.
+            return Self::jump(config, history, best, raw);
+        }
+        if candidates.len() == 1 || config.ambiguity() == Ambiguity::Best {
+            return Self::jump(config, history, candidates.swap_remove(0), raw);
+        }
+
+        match config.ambiguity() {
+            Ambiguity::Prompt => {
+                let candidates = candidates.into_iter()
+                    .map(|it| (it, PathState::Present))
+                    .collect::<Vec<_>>();
+                let entry = Self::select(&format!("Pick a directory matching `{}`", &raw[1..]), &candidates)?;
+                Self::jump(config, history, entry, raw)
             }
+            _ => bail!(CdxError::Ambiguous(raw, candidates.into_iter().map(|it| it.canonical).collect())),
+        }
+    }
+
+    fn jump(config: &CdxConfig, history: &mut History, entry: Entry, raw: String) -> anyhow::Result<PathBuf> {
+        let p = Self::available(history, &entry)?;
+        let p = match config.path_mode() {
+            PathMode::Logical => Self::logical(&entry),
+            PathMode::Physical => p,
+        };
+        history.append_last(entry.with_raw(raw));
+        Ok(p)
+    }
+
+    // the logical path may have been re-pointed at another directory since, or removed.
+    fn logical(entry: &Entry) -> PathBuf {
+        let logical = PathBuf::from(&entry.logical);
+        match logical.canonicalize() {
+            Ok(it) if it == Path::new(&entry.canonical) => logical,
+            _ => PathBuf::from(&entry.canonical),
         }
-        bail!("[error] failed to find history by shortcut `{}`", input);
+    }
+
+    pub fn bookmark(config: &CdxConfig, history: &mut History, name: String) -> anyhow::Result<PathBuf> {
+        let bookmarks = Bookmarks::open()?;
+        let Some(bookmark) = bookmarks.get(&name) else {
+            bail!(CdxError::NoMatch(format!("[error] no bookmark named `{}`, see --bookmarks", name)));
+        };
+        let raw = format!("@{}", name);
+        let entry = Entry::new(&raw, &bookmark.canonical).with_logical(&bookmark.logical);
+        Self::jump(config, history, entry, raw)
     }
 
     pub fn revision(config: &CdxConfig, history: &mut History, revision: usize) -> anyhow::Result<PathBuf> {
         let search_size = config.search_size();
-        if revision <= 0 || revision > search_size {
-            bail!("[error] revision {} is out of range. (0 < r <={})", revision, search_size);
+        if revision == 0 || revision > search_size {
+            bail!(CdxError::NoMatch(format!("[error] revision {} is out of range. (0 < r <={})", revision, search_size)));
         }
 
         if let Some(entry) = history.read(search_size).get(revision) {
-            let p = PathBuf::from(&entry.canonical);
-            let to_append = entry.with_raw(format!(":{}", revision));
-            history.append_last(to_append);
-            return Ok(p);
+            return Self::jump(config, history, entry.clone(), format!(":{}", revision));
         }
 
-        bail!("failed to find history by revision {}", revision);
+        bail!(CdxError::NoMatch(format!("[error] failed to find history by revision {}", revision)));
+    }
+
+    /// Goes back or forth in the session of the shell, by `offset` directories.
+    pub fn step(config: &CdxConfig, history: &mut History, session: Option<&mut Session>, offset: isize) -> anyhow::Result<PathBuf> {
+        let Some(session) = session else {
+            bail!("[error] going back and forth requires the shell integration of `shx-cdx init`, which sets {}", session::SESSION);
+        };
+        let raw = match offset {
+            ..0 => format!(":{}", offset),
+            _ => format!(":+{}", offset),
+        };
+        let Some(dir) = session.step(offset) else {
+            let direction = if offset < 0 { "back" } else { "forth" };
+            bail!(CdxError::NoMatch(format!("[error] no directory to go {} to in this session", direction)));
+        };
+        let path = dir.canonicalize().map_err(|_| CdxError::Vanished(dir.clone()))?;
+        let entry = Entry::new(&raw, &path).with_logical(&dir);
+        Self::jump(config, history, entry, raw)
+    }
+
+    // `cd -` follows the visiting order of the shell, or of all shells without a session.
+    fn previous(config: &CdxConfig, history: &mut History, session: Option<&Session>) -> anyhow::Result<PathBuf> {
+        if let Some(previous) = session.and_then(Session::previous) {
+            return Ok(previous);
+        }
+        match history.recent(config.search_size()).get(2) {
+            Some(entry) => Ok(Self::logical(entry)),
+            None => bail!(CdxError::NoMatch("[error] failed to find previous directory".to_string())),
+        }
     }
 
     pub fn interactive(config: &CdxConfig, history: &mut History) -> anyhow::Result<PathBuf> {
-        let search_size = config.search_size();
+        let entries = history.read_probed(config.search_size());
+        let entry = Self::select("Pick a directory to change", &entries)?;
+        Self::jump(config, history, entry, ":(selected)".to_string())
+    }
+
+    // prompts to pick one of `entries`, filtering them fuzzily as the user types.
+    fn select(message: &str, entries: &[(Entry, PathState)]) -> anyhow::Result<Entry> {
         let theme = Theme::default();
-        let selections = history.read(search_size)
+        let query = Query::default();
+        let selections = entries
             .iter()
             .enumerate()
-            .map(|(index, entry)| entry.prettify(index, &theme))
+            .map(|(index, (entry, state))| {
+                entry.prettify(index, &theme)
+                    .with_query(Rc::clone(&query))
+                    .with_note(state.note())
+            })
             .collect::<Vec<_>>();
 
+        // inquire sorts the filtered options unstably, so fold the frecency order into the fuzzy score.
+        let size = selections.len() as i64;
+        let scorer = |input: &str, option: &StyledEntry, _: &str, index: usize| {
+            query.replace(input.to_string());
+            let score = if input.is_empty() { 0 } else { FuzzyMatcher::new(input).score(option.canonical())? };
+            Some(score * size + (size - index as i64))
+        };
+
         let render_config = theme.render_config();
-        let selection = Select::new("Pick a directory to change", selections)
-            .with_help_message("Use arrow keys to navigate, Enter to select")
+        let selection = Select::new(message, selections)
+            .with_help_message("Use arrow keys to navigate, type to filter fuzzily, Enter to select")
             .with_render_config(render_config)
-            .prompt()?;
+            .with_scorer(&scorer)
+            .raw_prompt()
+            .map_err(|e| match e {
+                InquireError::OperationCanceled | InquireError::OperationInterrupted => CdxError::Cancelled.into(),
+                e => anyhow::Error::from(e),
+            })?;
+
+        Ok(entries[selection.index].0.clone())
+    }
 
-        let to_append = selection.to_entry()
-            .with_raw(":(selected)".to_string());
-        let p = PathBuf::from(&to_append.canonical);
-        history.append_last(to_append);
-        return Ok(p);
+    // history may hold directories which have been removed, or whose filesystem has been unmounted since.
+    fn available(history: &History, entry: &Entry) -> anyhow::Result<PathBuf> {
+        let path = PathBuf::from(&entry.canonical);
+        match history.probe(entry) {
+            PathState::Present => Ok(path),
+            PathState::Missing => bail!(CdxError::Vanished(path)),
+            PathState::Unavailable => bail!(CdxError::Unavailable(path)),
+            PathState::Denied => bail!(CdxError::Denied(path)),
+        }
     }
 }
@@ -1,11 +1,25 @@
 use std::str::FromStr;
 
-use clap::{Args, Parser};
+use anyhow::{anyhow, bail};
+use clap::{Parser, Subcommand};
+use globset::Glob;
+use regex::Regex;
 
+use crate::exchange::Format;
+use crate::import::Source;
+use crate::listing;
+use crate::listing::{Listing, Sort};
+use crate::matcher::pattern;
 use crate::opts::Opts;
+use crate::output::OutputFormat;
+use crate::shell::{Integration, Shell};
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[arg(
         index = 1,
         help = "Specifies the directory to jump.",
@@ -15,6 +29,29 @@ pub struct Cli {
     )]
     pub dir: Option<DirArgs>,
 
+    #[arg(
+        index = 2,
+        help = "More keywords narrowing down a shortcut, e.g. `:work api`, or the replacement of `cdx old new`.",
+        requires = "dir",
+        conflicts_with = "opts"
+    )]
+    pub keywords: Vec<String>,
+
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        help = "List the directories a shortcut, regex or glob matches, best first, instead of jumping",
+        requires = "dir",
+    )]
+    pub query: bool,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        help = "Print listings and errors in a format for scripts, or as plain text without colors",
+    )]
+    pub format: Option<OutputFormat>,
+
     #[arg(
         short = 's',
         long,
@@ -24,27 +61,267 @@ pub struct Cli {
     )]
     pub show_history: bool,
 
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        help = "Show only the history within the project the current directory belongs to",
+        requires = "show_history",
+    )]
+    pub project: bool,
+
+    #[arg(
+        long,
+        value_name = "ORDER",
+        help = "Order to show the history in",
+        requires = "show_history",
+    )]
+    pub sort: Option<Sort>,
+
+    #[arg(
+        long,
+        value_name = "TEXT",
+        help = "Show only the directories whose path contains the text",
+        requires = "show_history",
+    )]
+    pub filter: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Show only the directories within the given one",
+        requires = "show_history",
+    )]
+    pub under: Option<String>,
+
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        help = "Show only the directories which can be changed to",
+        requires = "show_history",
+    )]
+    pub existing: bool,
+
+    #[arg(
+        long,
+        value_name = "WHEN",
+        help = "Show only the directories visited since a date like `2024-05-01`, or for an age like `7d`, `12h` or `30m`",
+        value_parser = |s: &str| listing::parse_since(s),
+        requires = "show_history",
+    )]
+    pub since: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "COUNT",
+        help = "How many directories to show, instead of `search_size` of the config",
+        requires = "show_history",
+    )]
+    pub limit: Option<usize>,
+
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        help = "Show the whole history",
+        requires = "show_history",
+        conflicts_with = "limit",
+    )]
+    pub all: bool,
+
     #[arg(
         short = 'l',
         long,
-        help = "Learn",
+        value_name = "DIR",
+        help = "Learn the directories under the given one, without visiting them",
         group = "opts",
     )]
     pub learn: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DEPTH",
+        help = "How deep to descend while learning, instead of `learn_depth` of the config",
+        requires = "learn",
+    )]
+    pub depth: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Pin a directory so that it is never evicted from history",
+        group = "opts",
+    )]
+    pub pin: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Unpin a directory",
+        group = "opts",
+    )]
+    pub unpin: Option<String>,
+
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        help = "Remove directories which no longer exist from history",
+        group = "opts",
+    )]
+    pub prune: bool,
+
+    #[arg(
+        long,
+        value_names = ["NAME", "DIR"],
+        num_args = 1..=2,
+        help = "Name a directory, the current one by default, to jump to it with `@NAME`",
+        group = "opts",
+    )]
+    pub mark: Option<Vec<String>>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Remove a bookmark",
+        group = "opts",
+    )]
+    pub unmark: Option<String>,
+
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        help = "List bookmarks",
+        group = "opts",
+    )]
+    pub bookmarks: bool,
+
+    #[arg(
+        long,
+        value_name = "SOURCE",
+        help = "Import history from another directory jumper",
+        group = "opts",
+    )]
+    pub import: Option<Source>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Database to import from, instead of the default location of the source",
+        requires = "import",
+    )]
+    pub import_path: Option<String>,
+
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        help = "Replace the history with the imported one, instead of merging into it",
+        requires = "import",
+    )]
+    pub replace: bool,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        help = "Export the whole history",
+        group = "opts",
+    )]
+    pub export: Option<Format>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "File to export to, instead of stdout",
+        requires = "export",
+    )]
+    pub output: Option<String>,
+}
+
+/// Commands used by shell integration rather than typed by hand.
+/// Pass `--` first to jump to a directory sharing their name, e.g. `shx-cdx -- add`.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Record a directory in history without jumping to it.
+    Add {
+        #[arg(default_value = ".", help = "The directory to record")]
+        path: String,
+    },
+    /// Print a prompt hook which records every directory change of the shell.
+    Hook {
+        shell: Shell,
+    },
+    /// Print the shell integration, e.g. `eval "$(shx-cdx init zsh)"`.
+    Init {
+        shell: Shell,
+        #[arg(long, default_value = "cdx", help = "Name of the function to define")]
+        cmd: String,
+        #[arg(long, help = "Replace `cd` with the function, too")]
+        cd: bool,
+        #[arg(long, help = "Do not install the prompt hook which records plain `cd`")]
+        no_hook: bool,
+    },
 }
 
 impl Cli {
-    pub fn dir(&self) -> DirArgs {
-        self.dir.clone().unwrap_or(DirArgs::BulitIn("".to_string()))
+    pub fn dir(&self) -> anyhow::Result<DirArgs> {
+        let dir = self.dir.clone().unwrap_or(DirArgs::BulitIn("".to_string()));
+        match dir {
+            DirArgs::Shortcut(mut keywords) => {
+                keywords.extend(self.keywords.iter().cloned());
+                Ok(DirArgs::Shortcut(keywords))
+            }
+            DirArgs::Scoped(mut keywords) => {
+                keywords.extend(self.keywords.iter().cloned());
+                Ok(DirArgs::Scoped(keywords))
+            }
+            // `cdx old new`, like `cd old new` of zsh.
+            DirArgs::BulitIn(old) if self.keywords.len() == 1 => {
+                Ok(DirArgs::Substitute(old, self.keywords[0].clone()))
+            }
+            _ if !self.keywords.is_empty() => {
+                bail!("[error] unexpected arguments `{}`", self.keywords.join(" "))
+            }
+            dir => Ok(dir),
+        }
     }
 
     pub fn opt(&self) -> Option<Opts> {
-        if self.show_history {
-            Some(Opts::ShowHistory)
-        } else if let Some(learn) = &self.learn {
-            Some(Opts::Learn(learn.clone()))
+        if let Some(command) = &self.command {
+            match command {
+                Command::Add { path } => Some(Opts::Add(path.clone())),
+                Command::Hook { shell } => Some(Opts::Hook(*shell)),
+                Command::Init { shell, cmd, cd, no_hook } => Some(Opts::Init(*shell, Integration {
+                    cmd: cmd.clone(),
+                    wrap_cd: *cd,
+                    hook: !no_hook,
+                })),
+            }
+        } else if self.show_history {
+            Some(Opts::ShowHistory(Listing {
+                sort: self.sort,
+                filter: self.filter.clone(),
+                under: self.under.clone(),
+                existing: self.existing,
+                since: self.since,
+                limit: self.limit,
+                all: self.all,
+                project: self.project,
+            }))
+        } else if let Some(pin) = &self.pin {
+            Some(Opts::Pin(pin.clone()))
+        } else if let Some(unpin) = &self.unpin {
+            Some(Opts::Unpin(unpin.clone()))
+        } else if self.prune {
+            Some(Opts::Prune)
+        } else if let Some(mark) = &self.mark {
+            Some(Opts::Mark(mark[0].clone(), mark.get(1).cloned()))
+        } else if let Some(name) = &self.unmark {
+            Some(Opts::Unmark(name.clone()))
+        } else if self.bookmarks {
+            Some(Opts::Bookmarks)
+        } else if let Some(source) = self.import {
+            Some(Opts::Import(source, self.import_path.clone(), self.replace))
+        } else if let Some(format) = self.export {
+            Some(Opts::Export(format, self.output.clone()))
         } else {
-            None
+            self.learn.clone().map(|it| Opts::Learn(it, self.depth))
         }
     }
 }
@@ -55,18 +332,39 @@ impl FromStr for DirArgs {
     fn from_str(s: &str) -> anyhow::Result<Self> {
         if s.starts_with("\\") {
             let dir = s.strip_prefix("\\").unwrap().to_string();
-            println!("dir: {}", dir);
             return Ok(DirArgs::BulitIn(dir));
         }
+        if let Some(name) = s.strip_prefix("@").filter(|it| !it.is_empty()) {
+            return Ok(DirArgs::Bookmark(name.to_string()));
+        }
+        if let Some(levels) = s.strip_prefix("..").and_then(|it| it.parse::<usize>().ok()).filter(|it| *it > 0) {
+            return Ok(DirArgs::Ancestor(levels));
+        }
         match s.strip_prefix(":") {
             None => Ok(DirArgs::BulitIn(s.to_string())),
             Some(it) => {
                 if it.is_empty() {
                     Ok(DirArgs::Interactive)
+                } else if it == "^" {
+                    Ok(DirArgs::ProjectRoot)
+                } else if let Some(name) = it.strip_prefix('^') {
+                    Ok(DirArgs::NamedAncestor(name.to_string()))
+                } else if let Some(keyword) = it.strip_prefix('=').filter(|it| !it.is_empty()) {
+                    Ok(DirArgs::Scoped(vec![keyword.to_string()]))
+                } else if let Some(name) = it.strip_prefix('~').filter(|it| !it.is_empty()) {
+                    Ok(DirArgs::Sibling(name.to_string()))
+                } else if let Some(step) = parse_step(it) {
+                    Ok(DirArgs::Step(step))
                 } else if let Ok(revision) = it.parse::<usize>() {
                     Ok(DirArgs::Revision(revision))
+                } else if let Some(regex) = it.strip_prefix('/').and_then(|it| it.strip_suffix('/')).filter(|it| !it.is_empty()) {
+                    let regex = Regex::new(regex).map_err(|e| anyhow!("[error] invalid regex `{}`: {}", regex, e))?;
+                    Ok(DirArgs::Regex(regex))
+                } else if pattern::is_glob(it) {
+                    let glob = pattern::glob(it).map_err(|e| anyhow!("[error] invalid glob `{}`: {}", it, e))?;
+                    Ok(DirArgs::Glob(glob))
                 } else {
-                    Ok(DirArgs::Shortcut(it.to_string()))
+                    Ok(DirArgs::Shortcut(vec![it.to_string()]))
                 }
             }
         }
@@ -77,6 +375,38 @@ impl FromStr for DirArgs {
 pub enum DirArgs {
     BulitIn(String),
     Interactive,
-    Shortcut(String),
+    Shortcut(Vec<String>),
+    /// `:=keyword`, a shortcut matching only the directories within the current project.
+    Scoped(Vec<String>),
     Revision(usize),
+    /// `:/regex/`, matched anywhere in the path.
+    Regex(Regex),
+    /// A shortcut with `*`, `?` or `[`, matched against the end of the path.
+    Glob(Glob),
+    /// `@name`, a directory named with `--mark`.
+    Bookmark(String),
+    /// `:-`, `:+`, `:-3` or `:+2`, going back or forth in the session of the shell.
+    Step(isize),
+    /// `..3`, the directory three levels up.
+    Ancestor(usize),
+    /// `:^name`, the nearest ancestor named `name`.
+    NamedAncestor(String),
+    /// `:^`, the root of the project the current directory belongs to.
+    ProjectRoot,
+    /// `old new`, the current directory with its component `old` replaced by `new`.
+    Substitute(String, String),
+    /// `:~name`, the sibling named `name` or starting with it, else the nearest directory named `name` next to an ancestor.
+    Sibling(String),
+}
+
+fn parse_step(s: &str) -> Option<isize> {
+    let (sign, count) = match s.split_at_checked(1)? {
+        ("-", count) => (-1, count),
+        ("+", count) => (1, count),
+        _ => return None,
+    };
+    match count {
+        "" => Some(sign),
+        count => count.parse::<isize>().ok().filter(|it| *it > 0).map(|it| sign * it),
+    }
 }
\ No newline at end of file
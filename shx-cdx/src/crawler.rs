@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use ignore::{Walk, WalkBuilder};
+use shx_config::cdx::CdxConfig;
+
+use crate::matcher::Matcher;
+use crate::project::is_project_root;
+
+/// Walks a directory tree to discover directories worth jumping to,
+/// skipping hidden directories and whatever ignore files exclude.
+pub struct Crawler {
+    depth: usize,
+    ignore: Vec<String>,
+    markers: Vec<String>,
+}
+
+pub struct Discovered {
+    pub path: PathBuf,
+    pub project: bool,
+}
+
+impl Crawler {
+    pub fn new(config: &CdxConfig) -> Self {
+        Crawler {
+            depth: config.learn_depth(),
+            ignore: config.learn_ignore(),
+            markers: config.project_markers(),
+        }
+    }
+
+    pub fn with_depth(self, depth: Option<usize>) -> Self {
+        Crawler {
+            depth: depth.unwrap_or(self.depth),
+            ..self
+        }
+    }
+
+    pub fn crawl(&self, root: &Path) -> Vec<Discovered> {
+        self.walk(root)
+            .filter_map(Result::ok)
+            .filter(|it| it.file_type().is_some_and(|it| it.is_dir()))
+            .map(|it| Discovered {
+                project: is_project_root(it.path(), &self.markers),
+                path: it.into_path(),
+            })
+            .collect()
+    }
+
+    /// The directory below `root` which matches best, the shallower the better between equal matches.
+    /// Gives up walking at `deadline`, returning the best match found by then.
+    pub fn find(&self, root: &Path, matcher: &dyn Matcher, deadline: Instant) -> Option<PathBuf> {
+        let mut best: Option<(f64, PathBuf)> = None;
+        for entry in self.walk(root).filter_map(Result::ok) {
+            if Instant::now() > deadline {
+                break;
+            }
+            if entry.depth() == 0 || !entry.file_type().is_some_and(|it| it.is_dir()) {
+                continue;
+            }
+            let Some(bonus) = matcher.bonus(&entry.path().display().to_string()) else {
+                continue;
+            };
+            let better = best.as_ref().is_none_or(|(score, path)| {
+                bonus > *score || (bonus == *score && entry.path().as_os_str().len() < path.as_os_str().len())
+            });
+            if better {
+                best = Some((bonus, entry.into_path()));
+            }
+        }
+        best.map(|(_, path)| path)
+    }
+
+    fn walk(&self, root: &Path) -> Walk {
+        let ignore = self.ignore.clone();
+        WalkBuilder::new(root)
+            .max_depth(Some(self.depth))
+            .require_git(false)
+            .filter_entry(move |it| !ignore.iter().any(|name| it.file_name() == name.as_str()))
+            .build()
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// Failures the shell integration and scripts can tell apart by the exit code.
+/// Any other error exits with 1, and invalid arguments exit with 2.
+#[derive(Debug)]
+pub enum CdxError {
+    /// Nothing matched the given directory, shortcut or revision.
+    NoMatch(String),
+    /// The interactive selection was cancelled.
+    Cancelled,
+    /// The matched directory no longer exists.
+    Vanished(PathBuf),
+    /// The config file could not be read or parsed.
+    Config(anyhow::Error),
+    /// The history database could not be read or written.
+    Database(anyhow::Error),
+    /// A shortcut matched several directories, and the config asks to fail then.
+    Ambiguous(String, Vec<String>),
+    /// The filesystem of the matched directory is not mounted, or does not answer.
+    Unavailable(PathBuf),
+    /// The matched directory may not be entered.
+    Denied(PathBuf),
+}
+
+impl CdxError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CdxError::NoMatch(_) => 3,
+            CdxError::Cancelled => 4,
+            CdxError::Vanished(_) => 5,
+            CdxError::Config(_) => 6,
+            CdxError::Database(_) => 7,
+            CdxError::Ambiguous(..) => 8,
+            CdxError::Unavailable(_) => 9,
+            CdxError::Denied(_) => 10,
+        }
+    }
+
+    /// A name for the failure, for the errors printed as JSON.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CdxError::NoMatch(_) => "no_match",
+            CdxError::Cancelled => "cancelled",
+            CdxError::Vanished(_) => "vanished",
+            CdxError::Config(_) => "config",
+            CdxError::Database(_) => "database",
+            CdxError::Ambiguous(..) => "ambiguous",
+            CdxError::Unavailable(_) => "unavailable",
+            CdxError::Denied(_) => "denied",
+        }
+    }
+
+    /// The exit code of any error, looking for a `CdxError` along its chain.
+    pub fn exit_code_of(error: &anyhow::Error) -> u8 {
+        error.chain()
+            .find_map(|it| it.downcast_ref::<CdxError>())
+            .map(CdxError::exit_code)
+            .unwrap_or(1)
+    }
+}
+
+impl Display for CdxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CdxError::NoMatch(message) => write!(f, "{}", message),
+            CdxError::Cancelled => write!(f, "[error] selection cancelled"),
+            CdxError::Vanished(path) => write!(f, "[error] directory {} no longer exists", path.display()),
+            CdxError::Unavailable(path) => write!(f, "[error] directory {} is unavailable, its filesystem is not mounted or does not answer", path.display()),
+            CdxError::Denied(path) => write!(f, "[error] directory {} may not be entered", path.display()),
+            CdxError::Config(e) | CdxError::Database(e) => write!(f, "{:#}", e),
+            CdxError::Ambiguous(input, candidates) => {
+                write!(f, "[error] `{}` matches {} directories:", input, candidates.len())?;
+                candidates.iter().try_for_each(|it| write!(f, "\n  {}", it))
+            }
+        }
+    }
+}
+
+impl std::error::Error for CdxError {}
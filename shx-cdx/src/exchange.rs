@@ -0,0 +1,58 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::history::Entry;
+
+/// Human-readable formats the whole history can be exported to, and imported back from.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Format {
+    /// One JSON object per line.
+    Jsonl,
+    Csv,
+    Toml,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TomlHistory {
+    entries: Vec<Entry>,
+}
+
+impl Format {
+    pub fn export(&self, entries: &[Entry]) -> anyhow::Result<String> {
+        let output = match self {
+            Format::Jsonl => entries.iter()
+                .map(serde_json::to_string)
+                .collect::<Result<Vec<_>, _>>()?
+                .join("\n"),
+            Format::Csv => {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                for entry in entries {
+                    writer.serialize(entry)?;
+                }
+                String::from_utf8(writer.into_inner()?)?
+            }
+            Format::Toml => toml::to_string(&TomlHistory { entries: entries.to_vec() })?,
+        };
+        Ok(output)
+    }
+
+    pub fn parse(&self, content: &str) -> anyhow::Result<Vec<Entry>> {
+        let entries = match self {
+            Format::Jsonl => content.lines()
+                .filter(|it| !it.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<Vec<_>, _>>()?,
+            Format::Csv => csv::Reader::from_reader(content.as_bytes())
+                .deserialize()
+                .collect::<Result<Vec<_>, _>>()?,
+            Format::Toml => toml::from_str::<TomlHistory>(content)?.entries,
+        };
+        // exports predating logical paths lack them.
+        Ok(entries.into_iter()
+            .map(|it| match it.logical.is_empty() {
+                true => Entry { logical: it.canonical.clone(), ..it },
+                false => it,
+            })
+            .collect())
+    }
+}
@@ -1,65 +1,447 @@
-use std::{fs, io};
-use std::fmt::{Debug, Display};
+use std::{fs, io, mem, process};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
-use bincode::Options;
 use serde::{Deserialize, Serialize};
+use shx_config::cdx::CdxConfig;
 use shx_config::config::path_for;
 
+use crate::error::CdxError;
+use crate::probe::{PathState, Prober};
+use crate::schema;
+
 const DB: &str = "cdx.db";
+const LOCK: &str = "cdx.db.lock";
+const BACKUP: &str = "cdx.db.bak";
+const CORRUPT: &str = "cdx.db.corrupt";
+/// What learned entries are recorded as, instead of the shortcut they were visited by.
+pub const LEARNED: &str = "(learned)";
 
-#[derive(Serialize, Deserialize)]
-pub struct History(Vec<Entry>);
+/// The cd history, backed by `cdx.db`.
+///
+/// Other shells may update the database while this one is open,
+/// so changes are recorded and replayed onto the latest database on `save`.
+pub struct History {
+    entries: Vec<Entry>,
+    changes: Vec<Change>,
+    frecency: Frecency,
+    max_size: usize,
+    aging_threshold: u32,
+    prober: Prober,
+}
+
+enum Change {
+    Visit(Entry),
+    Pin(Entry, bool),
+    Merge(Entry),
+    Learn(Entry),
+    Remove(Entry),
+    Clear,
+}
 
 impl History {
-    pub fn open() -> anyhow::Result<Self> {
-        let path = path_for(DB)?;
+    pub fn open(config: &CdxConfig) -> anyhow::Result<Self> {
+        let entries = {
+            let _lock = Self::lock().map_err(CdxError::Database)?;
+            Self::load().map_err(CdxError::Database)?
+        };
 
-        return match fs::read(&path) {
-            Ok(bytes) => {
-                let deserializer = bincode::options();
-                let history = deserializer.deserialize(&bytes)?;
-                Ok(history)
-            }
+        Ok(History {
+            entries,
+            changes: Vec::new(),
+            frecency: Frecency::new(config),
+            max_size: config.max_size(),
+            aging_threshold: config.aging_threshold(),
+            prober: Prober::new(config),
+        })
+    }
+
+    /// Takes an advisory lock on the database, released when the returned file is dropped.
+    fn lock() -> anyhow::Result<File> {
+        let path = path_for(LOCK)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("[fatal] cannot open lock file: {}", path.display()))?;
+        file.lock()
+            .with_context(|| format!("[fatal] cannot lock database: {}", path.display()))?;
+        Ok(file)
+    }
+
+    fn load() -> anyhow::Result<Vec<Entry>> {
+        let path = path_for(DB)?;
+        match fs::read(&path) {
+            Ok(bytes) => match schema::decode(&bytes) {
+                Ok((entries, version)) => {
+                    if version < schema::VERSION {
+                        Self::write(&schema::encode(&entries)?)?;
+                    }
+                    Ok(entries)
+                }
+                Err(e) if schema::is_newer(&bytes) => {
+                    Err(e).with_context(|| format!("[fatal] database {} was written by a newer shx-cdx", path.display()))
+                }
+                Err(e) => Self::recover(&path, e),
+            },
             Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                Ok(History(Vec::new()))
+                Ok(Vec::new())
             }
             Err(e) => {
                 Err(e).with_context(|| format!("[fatal] cannot not read from database: {}", path.display()))
             }
-        };
+        }
+    }
+
+    // moves the corrupt database aside, and falls back to the backup taken on the last save.
+    fn recover(path: &Path, error: anyhow::Error) -> anyhow::Result<Vec<Entry>> {
+        let corrupt = path_for(CORRUPT)?;
+        eprintln!("[warn] database {} is corrupt ({}), moved to {}", path.display(), error, corrupt.display());
+        fs::rename(path, &corrupt)
+            .with_context(|| format!("[fatal] cannot move corrupt database: {}", path.display()))?;
+
+        let backup = path_for(BACKUP)?;
+        match fs::read(&backup).map_err(anyhow::Error::from).and_then(|it| schema::decode(&it)) {
+            Ok((entries, _)) => {
+                fs::copy(&backup, path)
+                    .with_context(|| format!("[fatal] cannot restore database from backup: {}", backup.display()))?;
+                eprintln!("[warn] restored history from backup {}", backup.display());
+                Ok(entries)
+            }
+            Err(_) => {
+                eprintln!("[warn] no usable backup found, starting with an empty history");
+                Ok(Vec::new())
+            }
+        }
     }
 
+    /// Returns at most `size` entries, ordered by frecency score, learned ones after every visited one.
+    /// Entries with the same score are ordered by recency, and directories which cannot be found come last.
     pub fn read(&self, size: usize) -> Vec<Entry> {
-        self.0.iter()
+        self.read_probed(size)
+            .into_iter()
+            .map(|(it, _)| it)
+            .collect()
+    }
+
+    /// Same as `read`, along with whether the directory of each entry can be found.
+    pub fn read_probed(&self, size: usize) -> Vec<(Entry, PathState)> {
+        self.read_probed_by(size, |_| true)
+    }
+
+    /// Same as `read_probed`, keeping only the entries accepted by `filter`.
+    pub fn read_probed_by<F>(&self, size: usize, filter: F) -> Vec<(Entry, PathState)>
+    where
+        F: Fn(&Entry) -> bool,
+    {
+        let ranked = self.scored(usize::MAX).into_iter().filter(|(_, it)| filter(it));
+        self.probe_ranked(ranked, size)
+            .into_iter()
+            .map(|(_, entry, state)| (entry, state))
+            .collect()
+    }
+
+    /// Probes the ranked entries in order until `size` of them can be found, which come first, before those which cannot.
+    pub fn probe_ranked<I>(&self, ranked: I, size: usize) -> Vec<(f64, Entry, PathState)>
+    where
+        I: IntoIterator<Item = (f64, Entry)>,
+    {
+        let (mut present, mut absent) = (Vec::new(), Vec::new());
+        for (score, entry) in ranked {
+            if present.len() == size {
+                break;
+            }
+            match self.probe(&entry) {
+                PathState::Present => present.push((score, entry, PathState::Present)),
+                state => absent.push((score, entry, state)),
+            }
+        }
+        present.append(&mut absent);
+        present.truncate(size);
+        present
+    }
+
+    /// Whether the directory of the entry can be found, waiting only so long for its filesystem.
+    pub fn probe(&self, entry: &Entry) -> PathState {
+        self.prober.probe(entry)
+    }
+
+    /// Returns at most `size` entries along with their score, ordered as `read` does
+    /// but regardless of whether their directories can be found.
+    pub fn scored(&self, size: usize) -> Vec<(f64, Entry)> {
+        let now = now();
+        let mut entries = self.entries.iter()
             .rev()
+            .map(|it| (self.frecency.score(it, now), it))
+            .collect::<Vec<_>>();
+        entries.sort_by(|(a, x), (b, y)| x.is_learned().cmp(&y.is_learned()).then(b.total_cmp(a)));
+        entries.into_iter()
             .take(size)
-            .map(|it| it.clone())
+            .map(|(score, it)| (score, it.clone()))
             .collect()
     }
 
-    // if ever visited, promote to first. else append.
-    pub fn append_last(&mut self, new_entry: Entry) -> () {
-        self.0.retain(|it| &it.canonical != &new_entry.canonical);
-        self.0.push(new_entry);
+    /// Returns all entries, in visiting order.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns at most `size` entries, ordered by last access only.
+    pub fn recent(&self, size: usize) -> Vec<Entry> {
+        self.entries.iter()
+            .rev()
+            .take(size)
+            .cloned()
+            .collect()
+    }
+
+    pub fn append_last(&mut self, new_entry: Entry) {
+        let new_entry = self.locate(new_entry);
+        self.record(Change::Visit(new_entry));
+    }
+
+    /// Pins or unpins `new_entry`, adding it first if it was never visited.
+    pub fn pin(&mut self, new_entry: Entry, pinned: bool) {
+        let new_entry = self.locate(new_entry);
+        self.record(Change::Pin(new_entry, pinned));
+    }
+
+    /// Merges entries learned elsewhere: counts of an already known directory are added up,
+    /// and the latest access wins. They are aged together, so that their order does not matter.
+    /// Returns how many of them are kept.
+    pub fn merge<I>(&mut self, new_entries: I) -> usize
+    where
+        I: IntoIterator<Item = Entry>,
+    {
+        let changes = new_entries.into_iter()
+            .map(|it| Change::Merge(self.locate(it)))
+            .collect();
+        self.record_all(changes)
+    }
+
+    /// Adds entries discovered without being visited. Known directories are left untouched.
+    /// Returns how many of them are kept.
+    pub fn learn<I>(&mut self, new_entries: I) -> usize
+    where
+        I: IntoIterator<Item = Entry>,
+    {
+        let changes = new_entries.into_iter()
+            .map(|it| Change::Learn(self.locate(it)))
+            .collect();
+        self.record_all(changes)
+    }
+
+    /// Forgets the entry, even if pinned.
+    pub fn remove(&mut self, entry: Entry) {
+        self.record(Change::Remove(entry));
+    }
+
+    /// Forgets every entry, pinned ones included.
+    pub fn clear(&mut self) {
+        self.record(Change::Clear);
+    }
+
+    // records the filesystem a new entry lives on.
+    fn locate(&self, entry: Entry) -> Entry {
+        Entry {
+            mount: self.prober.mount_of(Path::new(&entry.canonical)),
+            ..entry
+        }
+    }
+
+    fn record(&mut self, change: Change) {
+        self.record_all(vec![change]);
+    }
+
+    // applies the changes before enforcing the limits once, and returns how many of their entries are kept.
+    fn record_all(&mut self, changes: Vec<Change>) -> usize {
+        let mut changed = HashSet::new();
+        for change in changes {
+            self.apply(&change);
+            if let Change::Visit(entry) | Change::Pin(entry, _) | Change::Merge(entry) | Change::Learn(entry) = &change {
+                changed.insert(entry.canonical.clone());
+            }
+            self.changes.push(change);
+        }
+        self.enforce_limits();
+        self.entries.iter()
+            .filter(|it| changed.contains(&it.canonical))
+            .count()
+    }
+
+    fn apply(&mut self, change: &Change) {
+        match change {
+            Change::Visit(entry) => self.visit(entry.clone()),
+            Change::Pin(entry, pinned) => self.set_pinned(entry.clone(), *pinned),
+            Change::Merge(entry) => self.merge_entry(entry.clone()),
+            Change::Learn(entry) => self.learn_entry(entry.clone()),
+            Change::Remove(entry) => self.entries.retain(|it| it.canonical != entry.canonical),
+            Change::Clear => self.entries.clear(),
+        }
+    }
+
+    // if ever visited, bump its count and promote to first. else append.
+    fn visit(&mut self, new_entry: Entry) {
+        let (count, pinned) = self.entries.iter()
+            .find(|it| it.canonical == new_entry.canonical)
+            .map(|it| (it.count.saturating_add(1), it.pinned))
+            .unwrap_or((new_entry.count, new_entry.pinned));
+        self.entries.retain(|it| it.canonical != new_entry.canonical);
+        self.entries.push(Entry { count, pinned, last_access: now(), ..new_entry });
+    }
+
+    fn set_pinned(&mut self, new_entry: Entry, pinned: bool) {
+        match self.entries.iter_mut().find(|it| it.canonical == new_entry.canonical) {
+            Some(entry) => entry.pinned = pinned,
+            None => self.entries.push(Entry { pinned, ..new_entry }),
+        }
+    }
+
+    fn merge_entry(&mut self, new_entry: Entry) {
+        match self.entries.iter_mut().find(|it| it.canonical == new_entry.canonical) {
+            Some(entry) => {
+                entry.count = entry.count.saturating_add(new_entry.count);
+                entry.last_access = entry.last_access.max(new_entry.last_access);
+                entry.pinned |= new_entry.pinned;
+                // visits recorded elsewhere make a learned entry a visited one.
+                if entry.is_learned() {
+                    entry.raw = new_entry.raw;
+                }
+            }
+            None => self.entries.push(new_entry),
+        }
+        // keep the entries in visiting order.
+        self.entries.sort_by_key(|it| it.last_access);
+    }
+
+    fn learn_entry(&mut self, new_entry: Entry) {
+        if self.entries.iter().any(|it| it.canonical == new_entry.canonical) {
+            return;
+        }
+        // learned entries are older than any visit, so they go first.
+        self.entries.insert(0, new_entry);
     }
 
-    pub fn save(&self) -> anyhow::Result<()> {
+    /// Ages every count down once the total exceeds the aging threshold,
+    /// then evicts the lowest-scoring entries beyond `max_size`, learned ones first.
+    /// Pinned entries are never dropped, nor is the latest visit aged away.
+    fn enforce_limits(&mut self) {
+        let total = self.entries.iter().map(|it| it.count as u64).sum::<u64>();
+        if total > self.aging_threshold as u64 {
+            let latest = self.entries.len().saturating_sub(1);
+            for (index, entry) in self.entries.iter_mut().enumerate() {
+                entry.count = (entry.count as u64 * 9 / 10) as u32;
+                if entry.pinned || index == latest {
+                    entry.count = entry.count.max(1);
+                }
+            }
+            self.entries.retain(|it| it.count > 0);
+        }
+
+        let excess = self.entries.len().saturating_sub(self.max_size);
+        if excess == 0 {
+            return;
+        }
+        let now = now();
+        // entries are kept in visiting order, so ties are broken by evicting the least recent first.
+        let mut candidates = self.entries.iter()
+            .enumerate()
+            .filter(|(_, it)| !it.pinned)
+            .map(|(index, it)| (it.is_learned(), self.frecency.score(it, now), index))
+            .collect::<Vec<_>>();
+        candidates.sort_by(|(x, a, i), (y, b, j)| y.cmp(x).then(a.total_cmp(b)).then(i.cmp(j)));
+        let evicted = candidates.into_iter()
+            .take(excess)
+            .map(|(_, _, index)| index)
+            .collect::<HashSet<_>>();
+
+        let mut index = 0;
+        self.entries.retain(|_| {
+            index += 1;
+            !evicted.contains(&(index - 1))
+        });
+    }
+
+    /// Replays the recorded changes onto the latest database, and replaces it atomically.
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        self.persist().map_err(|e| CdxError::Database(e).into())
+    }
+
+    fn persist(&mut self) -> anyhow::Result<()> {
+        if self.changes.is_empty() {
+            return Ok(());
+        }
+        let _lock = Self::lock()?;
+        self.entries = Self::load()?;
+        for change in mem::take(&mut self.changes) {
+            self.apply(&change);
+        }
+        self.enforce_limits();
+
+        Self::write(&schema::encode(&self.entries)?)
+    }
+
+    // writes to a temporary file first, so that a crash never leaves a truncated database behind.
+    fn write(bytes: &[u8]) -> anyhow::Result<()> {
         let path = path_for(DB)?;
-        let serializer = bincode::options();
-        let serialized = serializer.serialize(&self.0)?;
-        fs::write(&path, serialized)?;
+        let tmp = path_for(format!("{}.{}.tmp", DB, process::id()))?;
+        let mut file = File::create(&tmp)
+            .with_context(|| format!("[fatal] cannot write to database: {}", tmp.display()))?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+
+        if path.exists() {
+            let _ = fs::copy(&path, path_for(BACKUP)?);
+        }
+        fs::rename(&tmp, &path)
+            .with_context(|| format!("[fatal] cannot replace database: {}", path.display()))?;
         Ok(())
     }
 }
 
+/// Scores entries by visit count, weighted down exponentially by the time since the last access.
+pub struct Frecency {
+    half_life: u64,
+}
+
+impl Frecency {
+    pub fn new(config: &CdxConfig) -> Self {
+        Frecency { half_life: config.frecency_half_life().max(1) * 60 * 60 }
+    }
+
+    pub fn score(&self, entry: &Entry, now: u64) -> f64 {
+        let age = now.saturating_sub(entry.last_access) as f64;
+        entry.count as f64 * (-age / self.half_life as f64).exp2()
+    }
+}
+
+/// Seconds since the unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|it| it.as_secs())
+        .unwrap_or(0)
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entry {
     pub raw: String,
     pub canonical: String,
+    /// The path the directory was reached by, with its symlinks unresolved.
+    /// Entries are still told apart by `canonical`.
+    #[serde(default)]
+    pub logical: String,
+    /// The mount point of the filesystem the directory lives on, or empty if unknown.
+    #[serde(default)]
+    pub mount: String,
+    pub count: u32,
+    pub last_access: u64,
+    pub pinned: bool,
 }
 
 impl Entry {
@@ -68,16 +450,92 @@ impl Entry {
         S: Into<String>,
         P: AsRef<Path>,
     {
+        let canonical = canonical.as_ref().display().to_string();
         Entry {
             raw: raw.into(),
-            canonical: canonical.as_ref().display().to_string(),
+            logical: canonical.clone(),
+            canonical,
+            mount: String::new(),
+            count: 1,
+            last_access: now(),
+            pinned: false,
         }
     }
 
+    /// Whether the entry was learned by `--learn`, and never visited since.
+    pub fn is_learned(&self) -> bool {
+        self.raw == LEARNED
+    }
+
     pub fn with_raw(&self, raw: String) -> Self {
         Entry {
             raw,
-            canonical: self.canonical.clone(),
+            ..self.clone()
+        }
+    }
+
+    pub fn with_logical<P>(self, logical: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Entry {
+            logical: logical.as_ref().display().to_string(),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(config: &str) -> History {
+        let config = toml::from_str::<CdxConfig>(config).unwrap();
+        History {
+            entries: Vec::new(),
+            changes: Vec::new(),
+            frecency: Frecency::new(&config),
+            max_size: config.max_size(),
+            aging_threshold: config.aging_threshold(),
+            prober: Prober::new(&config),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn keeps_the_visit_which_ages_the_history() {
+        let mut history = history("aging_threshold = 3");
+        for name in ["a", "b", "c", "d"] {
+            history.append_last(Entry::new(name, format!("/tmp/{}", name)));
+        }
+
+        let entries = history.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].canonical, "/tmp/d");
+        assert_eq!(entries[0].count, 1);
+    }
+
+    #[test]
+    fn ages_merged_entries_alike() {
+        let mut history = history("aging_threshold = 1000");
+        let imported = (0..40)
+            .map(|it| Entry { count: 50, last_access: 1, ..Entry::new("", format!("/tmp/{}", it)) })
+            .collect::<Vec<_>>();
+
+        let kept = history.merge(imported);
+
+        assert_eq!(kept, 40);
+        assert!(history.entries().iter().all(|it| it.count == 45));
+    }
+
+    #[test]
+    fn ranks_learned_entries_after_visited_ones() {
+        let mut history = history("");
+        history.learn([Entry { count: 100, ..Entry::new(LEARNED, "/tmp/learned") }]);
+        history.merge([Entry { last_access: 1, ..Entry::new("old", "/tmp/old") }]);
+
+        let scored = history.scored(2);
+
+        assert_eq!(scored[0].1.canonical, "/tmp/old");
+        assert_eq!(scored[1].1.canonical, "/tmp/learned");
+    }
+}
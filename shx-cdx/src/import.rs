@@ -0,0 +1,264 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use clap::ValueEnum;
+use serde::Deserialize;
+use shx_config::config::home;
+
+use crate::exchange::Format;
+use crate::history::{now, Entry};
+
+/// Other directory jumpers whose databases can be imported into the history,
+/// and the formats the history itself can be exported to.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Source {
+    Zoxide,
+    Autojump,
+    Z,
+    Fasd,
+    Jsonl,
+    Csv,
+    Toml,
+}
+
+/// A directory as recorded by another tool, before it is mapped onto an `Entry`.
+struct Record {
+    path: String,
+    rank: f64,
+    last_access: Option<u64>,
+}
+
+impl Source {
+    /// Where the tool keeps its database by default, honoring its own environment variables.
+    pub fn default_path(&self) -> anyhow::Result<PathBuf> {
+        let path = match self {
+            Source::Zoxide => match std::env::var("_ZO_DATA_DIR") {
+                Ok(dir) => PathBuf::from(dir),
+                Err(_) => zoxide_dir()?,
+            }.join("db.zo"),
+            Source::Autojump => autojump_dir()?.join("autojump.txt"),
+            Source::Z => match std::env::var("_Z_DATA") {
+                Ok(file) => PathBuf::from(file),
+                Err(_) => home()?.join(".z"),
+            },
+            Source::Fasd => match std::env::var("_FASD_DATA") {
+                Ok(file) => PathBuf::from(file),
+                Err(_) => home()?.join(".fasd"),
+            },
+            Source::Jsonl | Source::Csv | Source::Toml => {
+                bail!("[error] importing from {} requires --import-path", self)
+            }
+        };
+        Ok(path)
+    }
+
+    /// Reads the entries of the database at `path`. Their `canonical` is still the path
+    /// as recorded by the source, which may not be canonical nor exist anymore.
+    pub fn read(&self, path: &Path) -> anyhow::Result<Vec<Entry>> {
+        let bytes = fs::read(path)
+            .with_context(|| format!("[error] cannot read {} database: {}", self, path.display()))?;
+        let content = String::from_utf8_lossy(&bytes);
+        let records = match self {
+            Source::Zoxide => read_zoxide(&bytes),
+            Source::Autojump => read_autojump(&content),
+            Source::Z | Source::Fasd => read_z(&content),
+            Source::Jsonl => return self.parse(Format::Jsonl, &content, path),
+            Source::Csv => return self.parse(Format::Csv, &content, path),
+            Source::Toml => return self.parse(Format::Toml, &content, path),
+        };
+        let records = records.with_context(|| format!("[error] cannot parse {} database: {}", self, path.display()))?;
+        Ok(records.iter()
+            .map(|it| it.to_entry(format!("({})", self)))
+            .collect())
+    }
+
+    fn parse(&self, format: Format, content: &str, path: &Path) -> anyhow::Result<Vec<Entry>> {
+        format.parse(content)
+            .with_context(|| format!("[error] cannot parse {} file: {}", self, path.display()))
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => write!(f, "{}", value.get_name()),
+            None => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl Record {
+    /// Maps the rank onto a visit count, keeping at least a single visit.
+    fn to_entry(&self, raw: String) -> Entry {
+        Entry {
+            count: self.rank.round().clamp(1.0, u32::MAX as f64) as u32,
+            last_access: self.last_access.unwrap_or_else(now),
+            ..Entry::new(raw, &self.path)
+        }
+    }
+}
+
+// both tools follow `XDG_DATA_HOME` only off macOS, where each has a directory of its own under `~/Library`.
+#[cfg(target_os = "macos")]
+fn zoxide_dir() -> anyhow::Result<PathBuf> {
+    Ok(home()?.join("Library").join("Application Support").join("zoxide"))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn zoxide_dir() -> anyhow::Result<PathBuf> {
+    Ok(data_dir()?.join("zoxide"))
+}
+
+#[cfg(target_os = "macos")]
+fn autojump_dir() -> anyhow::Result<PathBuf> {
+    Ok(home()?.join("Library").join("autojump"))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn autojump_dir() -> anyhow::Result<PathBuf> {
+    Ok(data_dir()?.join("autojump"))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn data_dir() -> anyhow::Result<PathBuf> {
+    match std::env::var("XDG_DATA_HOME") {
+        Ok(dir) => Ok(PathBuf::from(dir)),
+        Err(_) => Ok(home()?.join(".local").join("share")),
+    }
+}
+
+#[derive(Deserialize)]
+struct ZoxideDir {
+    path: String,
+    rank: f64,
+    last_accessed: u64,
+}
+
+// zoxide prefixes its bincode-encoded directories with a format version.
+fn read_zoxide(bytes: &[u8]) -> anyhow::Result<Vec<Record>> {
+    const VERSION: u32 = 3;
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let (version, dirs) = bytes.split_at(4.min(bytes.len()));
+    let version = bincode::deserialize::<u32>(version)?;
+    if version != VERSION {
+        bail!("unsupported zoxide database version {}", version);
+    }
+    let dirs = bincode::deserialize::<Vec<ZoxideDir>>(dirs)?;
+    Ok(dirs.into_iter()
+        .map(|it| Record { path: it.path, rank: it.rank, last_access: Some(it.last_accessed) })
+        .collect())
+}
+
+// each line is `<weight>\t<path>`.
+fn read_autojump(content: &str) -> anyhow::Result<Vec<Record>> {
+    content.lines()
+        .filter(|it| !it.trim().is_empty())
+        .map(|line| {
+            let (rank, path) = line.split_once('\t')
+                .with_context(|| format!("malformed line `{}`", line))?;
+            Ok(Record { path: path.to_string(), rank: rank.parse()?, last_access: None })
+        })
+        .collect()
+}
+
+// z.sh and fasd share the `<path>|<rank>|<time>` layout.
+fn read_z(content: &str) -> anyhow::Result<Vec<Record>> {
+    content.lines()
+        .filter(|it| !it.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.rsplitn(3, '|');
+            let (Some(time), Some(rank), Some(path)) = (fields.next(), fields.next(), fields.next()) else {
+                bail!("malformed line `{}`", line);
+            };
+            Ok(Record { path: path.to_string(), rank: rank.parse()?, last_access: Some(time.parse()?) })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    /// A directory as zoxide writes it.
+    #[derive(Serialize)]
+    struct ZoxideDir<'a> {
+        path: &'a str,
+        rank: f64,
+        last_accessed: u64,
+    }
+
+    fn zoxide(version: u32, dirs: &[ZoxideDir]) -> Vec<u8> {
+        let mut bytes = bincode::serialize(&version).unwrap();
+        bytes.extend(bincode::serialize(dirs).unwrap());
+        bytes
+    }
+
+    #[test]
+    fn reads_zoxide() {
+        let bytes = zoxide(3, &[
+            ZoxideDir { path: "/home/me/foo", rank: 12.5, last_accessed: 1714521600 },
+            ZoxideDir { path: "/home/me/bar", rank: 1.0, last_accessed: 1714525200 },
+        ]);
+
+        let records = read_zoxide(&bytes).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].path, "/home/me/foo");
+        assert_eq!(records[0].rank, 12.5);
+        assert_eq!(records[0].last_access, Some(1714521600));
+        assert_eq!(records[1].path, "/home/me/bar");
+    }
+
+    #[test]
+    fn reads_empty_zoxide() {
+        assert!(read_zoxide(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_other_zoxide_versions() {
+        let bytes = zoxide(4, &[ZoxideDir { path: "/home/me/foo", rank: 1.0, last_accessed: 0 }]);
+
+        assert!(read_zoxide(&bytes).is_err());
+    }
+
+    #[test]
+    fn reads_autojump() {
+        let records = read_autojump("22.4\t/home/me/foo\n\n10.0\t/home/me/with\ttab\n").unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].path, "/home/me/foo");
+        assert_eq!(records[0].rank, 22.4);
+        assert_eq!(records[0].last_access, None);
+        assert_eq!(records[1].path, "/home/me/with\ttab");
+    }
+
+    #[test]
+    fn rejects_malformed_autojump() {
+        assert!(read_autojump("/home/me/foo\n").is_err());
+        assert!(read_autojump("many\t/home/me/foo\n").is_err());
+    }
+
+    #[test]
+    fn reads_z() {
+        let records = read_z("/home/me/foo|7|1714521600\n/home/me/a|b|3.5|1714525200\n").unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].path, "/home/me/foo");
+        assert_eq!(records[0].rank, 7.0);
+        assert_eq!(records[0].last_access, Some(1714521600));
+        assert_eq!(records[1].path, "/home/me/a|b");
+        assert_eq!(records[1].rank, 3.5);
+    }
+
+    #[test]
+    fn rejects_malformed_z() {
+        assert!(read_z("/home/me/foo|7\n").is_err());
+        assert!(read_z("/home/me/foo|7|yesterday\n").is_err());
+    }
+}
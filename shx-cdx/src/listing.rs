@@ -0,0 +1,157 @@
+use std::cmp::Reverse;
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ValueEnum;
+
+use crate::history::{now, Entry};
+use crate::path::CanonicalPath;
+use crate::project::Project;
+
+/// Orders `--show-history` can list the history in.
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum Sort {
+    /// Highest frecency first, as shortcuts rank directories.
+    #[default]
+    Score,
+    /// Most recently visited first.
+    Recent,
+    /// Most visited first.
+    Count,
+    /// Alphabetically by path.
+    Path,
+}
+
+/// Which entries `--show-history` lists, and how.
+#[derive(Default)]
+pub struct Listing {
+    /// The order given, if any.
+    pub sort: Option<Sort>,
+    /// Text the path must contain.
+    pub filter: Option<String>,
+    /// Directory the path must lie within, as given.
+    pub under: Option<String>,
+    /// Whether to list only the directories which can be changed to.
+    pub existing: bool,
+    /// Seconds since the unix epoch the last visit must not be older than.
+    pub since: Option<u64>,
+    /// How many entries to list, instead of `search_size`.
+    pub limit: Option<usize>,
+    /// Whether to list every entry.
+    pub all: bool,
+    /// Whether to list only the history within the current project.
+    pub project: bool,
+}
+
+impl Listing {
+    /// Whether `entry` passes every filter, apart from `existing` which needs probing,
+    /// with `under` and `project` resolved by the caller.
+    pub fn accepts(&self, entry: &Entry, under: Option<&CanonicalPath>, project: Option<&Project>) -> bool {
+        self.filter.as_ref().is_none_or(|filter| entry.canonical.contains(filter.as_str()) || entry.logical.contains(filter.as_str()))
+            && under.is_none_or(|under| Path::new(&entry.canonical).starts_with(under) || Path::new(&entry.logical).starts_with(under.logical()))
+            && self.since.is_none_or(|since| entry.last_access >= since)
+            && project.is_none_or(|it| it.contains(entry))
+    }
+
+    /// Orders the scored entries, which come ordered by score already.
+    pub fn sort(&self, scored: &mut [(f64, Entry)]) {
+        match self.sort.unwrap_or_default() {
+            Sort::Score => {}
+            Sort::Recent => scored.sort_by_key(|(_, it)| Reverse(it.last_access)),
+            Sort::Count => scored.sort_by_key(|(_, it)| Reverse(it.count)),
+            Sort::Path => scored.sort_by(|(_, a), (_, b)| a.canonical.cmp(&b.canonical)),
+        }
+    }
+}
+
+/// Parses `--since`, either a date like `2024-05-01`, taken as UTC midnight,
+/// or an age like `30m`, `12h`, `7d` or `2w`.
+pub fn parse_since(s: &str) -> anyhow::Result<u64> {
+    if let Some(date) = parse_date(s) {
+        return Ok(date);
+    }
+    let unit = match s.chars().last() {
+        Some('m') => 60,
+        Some('h') => 60 * 60,
+        Some('d') => 24 * 60 * 60,
+        Some('w') => 7 * 24 * 60 * 60,
+        _ => 0,
+    };
+    s.get(..s.len().saturating_sub(1))
+        .and_then(|it| it.parse::<u64>().ok())
+        .filter(|_| unit > 0)
+        .map(|it| now().saturating_sub(it.saturating_mul(unit)))
+        .ok_or_else(|| anyhow!("expected a date like `2024-05-01` or an age like `7d`, `12h`, `30m` or `2w`"))
+}
+
+// seconds from the unix epoch to the UTC midnight of `YYYY-MM-DD`.
+fn parse_date(s: &str) -> Option<u64> {
+    let mut parts = s.splitn(3, '-').map(|it| it.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // days from the civil calendar, counting years from March so that the leap day comes last.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    u64::try_from(days * 24 * 60 * 60).ok()
+}
+
+/// How long ago `time` was, in its largest whole unit, e.g. `3h ago`.
+pub fn ago(time: u64, now: u64) -> String {
+    let age = now.saturating_sub(time);
+    match age {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", age / 60),
+        3600..86400 => format!("{}h ago", age / 3600),
+        _ => format!("{}d ago", age / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2024-05-01"), Some(1714521600));
+        assert_eq!(parse_date("2024-03-01"), Some(1709251200));
+        assert_eq!(parse_date("2000-02-29"), Some(951782400));
+    }
+
+    #[test]
+    fn rejects_malformed_dates() {
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("2024-05-00"), None);
+        assert_eq!(parse_date("2024-05"), None);
+        assert_eq!(parse_date("2024-05-x"), None);
+        assert_eq!(parse_date("1969-12-31"), None);
+        assert_eq!(parse_date("7d"), None);
+    }
+
+    #[test]
+    fn parses_ages() {
+        for (age, seconds) in [("30m", 30 * 60), ("12h", 12 * 60 * 60), ("7d", 7 * 24 * 60 * 60), ("2w", 14 * 24 * 60 * 60)] {
+            let before = now();
+            let since = parse_since(age).unwrap();
+            assert!((before - seconds..=now() - seconds).contains(&since), "{}", age);
+        }
+    }
+
+    #[test]
+    fn parses_dates_as_since() {
+        assert_eq!(parse_since("2024-05-01").unwrap(), 1714521600);
+    }
+
+    #[test]
+    fn rejects_malformed_since() {
+        for since in ["", "7", "d", "7y", "-7d", "7 d", "7é", "é"] {
+            assert!(parse_since(since).is_err(), "{}", since);
+        }
+    }
+}
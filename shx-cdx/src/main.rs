@@ -1,63 +1,124 @@
-use std::fmt::{Debug, Display};
-use std::io::BufRead;
+use std::io;
+use std::io::Write;
 use std::process::ExitCode;
-use std::str::FromStr;
 
-use anyhow::Context;
-use clap::{Args, Parser};
-use clap::builder::TypedValueParser;
+use clap::Parser;
 use shx_config::config::config;
 
-use theme::formatter::ToPretty;
-
 use crate::cd::CD;
 use crate::cli::{Cli, DirArgs};
+use crate::error::CdxError;
 use crate::history::History;
+use crate::matcher::pattern::{PathGlobMatcher, RegexMatcher};
 use crate::opts::Opts;
-use crate::theme::SelectTheme;
+use crate::session::Session;
 
 mod cli;
 mod history;
 mod path;
+mod probe;
 
 mod theme;
 mod opts;
+mod output;
+mod bookmark;
 mod cd;
+mod crawler;
+mod error;
+mod exchange;
+mod import;
+mod listing;
+mod matcher;
+mod schema;
+mod session;
+mod shell;
+mod project;
 
 fn main() -> ExitCode {
-    let exec = exec();
+    let cli = Cli::parse();
+    let format = cli.format;
+    if format.is_some() {
+        colored::control::set_override(false);
+    }
+    let exec = exec(cli);
     match exec {
-        Ok(it) => {
-            println!("{}", it);
-            ExitCode::SUCCESS
-        }
+        Ok(it) if it.is_empty() => ExitCode::SUCCESS,
+        Ok(it) => match writeln!(io::stdout().lock(), "{}", it) {
+            // a reader which stops early, like `head`, has got all it wanted.
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+                eprintln!("[fatal] cannot write output: {}", e);
+                ExitCode::FAILURE
+            }
+            _ => ExitCode::SUCCESS,
+        },
         Err(e) => {
-            println!("{}", e);
-            ExitCode::FAILURE
+            match format {
+                Some(format) => eprintln!("{}", format.error(&e)),
+                None => eprintln!("{:#}", e),
+            }
+            ExitCode::from(CdxError::exit_code_of(&e))
         }
     }
 }
 
-fn exec() -> anyhow::Result<String>
+fn exec(cli: Cli) -> anyhow::Result<String>
 {
-    let cli = Cli::parse();
-    let mut history = History::open()?;
-    let config = config()?.cdx_config;
+    // shell integration is evaluated on every shell startup, so it must not depend on the config nor the database.
+    let opt = match cli.opt() {
+        Some(Opts::Hook(shell)) => return Opts::hook(shell),
+        Some(Opts::Init(shell, integration)) => return Opts::init(shell, integration),
+        opt => opt,
+    };
+    let config = config().map_err(CdxError::Config)?.cdx_config;
+    let mut history = History::open(&config)?;
 
-    if let Some(opt) = cli.opt() {
+    if let Some(opt) = opt {
         match opt {
-            Opts::ShowHistory => Opts::show_history(config, history),
-            Opts::Learn(dir) => Opts::learn(dir),
+            Opts::ShowHistory(listing) => Opts::show_history(config, history, listing, cli.format),
+            Opts::Learn(dir, depth) => Opts::learn(config, history, dir, depth),
+            Opts::Pin(dir) => Opts::pin(history, dir, true),
+            Opts::Unpin(dir) => Opts::pin(history, dir, false),
+            Opts::Prune => Opts::prune(config, history),
+            Opts::Mark(name, dir) => Opts::mark(name, dir),
+            Opts::Unmark(name) => Opts::unmark(name),
+            Opts::Bookmarks => Opts::bookmarks(history, cli.format),
+            Opts::Import(source, path, replace) => Opts::import(history, source, path, replace),
+            Opts::Export(format, output) => Opts::export(history, format, output),
+            Opts::Add(dir) => Opts::add(history, dir),
+            Opts::Hook(_) | Opts::Init(..) => unreachable!("handled before loading the config"),
         }
+    } else if cli.query {
+        Opts::query(config, history, cli.dir()?, cli.format)
     } else {
-        let result = match cli.dir() {
-            DirArgs::BulitIn(dest) => CD::builtin(&config, &mut history, dest),
-            DirArgs::Shortcut(shortcut) => CD::shortcut(&config, &mut history, shortcut),
+        let mut session = Session::current()?;
+        let result = match cli.dir()? {
+            DirArgs::BulitIn(dest) => CD::builtin(&config, &mut history, session.as_ref(), dest),
+            DirArgs::Step(offset) => CD::step(&config, &mut history, session.as_mut(), offset),
+            DirArgs::Shortcut(shortcut) => CD::shortcut(&config, &mut history, shortcut, false),
+            DirArgs::Scoped(shortcut) => CD::shortcut(&config, &mut history, shortcut, true),
             DirArgs::Revision(revision) => CD::revision(&config, &mut history, revision),
+            DirArgs::Regex(regex) => {
+                let raw = format!(":/{}/", regex.as_str());
+                CD::pattern(&config, &mut history, &RegexMatcher::new(regex), raw)
+            }
+            DirArgs::Glob(glob) => {
+                let raw = format!(":{}", glob.glob().trim_start_matches("**/"));
+                CD::pattern(&config, &mut history, &PathGlobMatcher::new(&glob), raw)
+            }
+            DirArgs::Ancestor(levels) => CD::ancestor(&config, &mut history, levels),
+            DirArgs::NamedAncestor(name) => CD::named_ancestor(&config, &mut history, name),
+            DirArgs::ProjectRoot => CD::project_root(&config, &mut history),
+            DirArgs::Substitute(old, new) => CD::substitute(&config, &mut history, old, new),
+            DirArgs::Sibling(name) => CD::sibling(&config, &mut history, name),
+            DirArgs::Bookmark(name) => CD::bookmark(&config, &mut history, name),
             DirArgs::Interactive => CD::interactive(&config, &mut history),
         };
 
         history.save()?;
+        if let (Ok(dir), Some(session)) = (&result, session.as_mut()) {
+            session.visit(dir);
+            session.save()?;
+        }
         result.map(|it| format!("{}", it.display()))
     }
 }
@@ -0,0 +1,36 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher as _;
+
+use crate::matcher::Matcher;
+
+/// Matches skim-style: the characters of the pattern must appear in the path in order,
+/// scoring consecutive characters and those starting a path segment, a word or a camelCase hump higher.
+/// For example, `mcsvc` matches `micro-services`.
+///
+/// Matching is smart-case: case-insensitive unless the pattern has an uppercase letter.
+pub struct FuzzyMatcher {
+    pattern: String,
+    inner: SkimMatcherV2,
+}
+
+impl FuzzyMatcher {
+    pub fn new(pattern: &str) -> Self {
+        FuzzyMatcher { pattern: pattern.to_string(), inner: SkimMatcherV2::default().smart_case() }
+    }
+
+    pub fn score(&self, path: &str) -> Option<i64> {
+        self.inner.fuzzy_match(path, &self.pattern)
+    }
+
+    /// The char indices of `path` matched by the pattern, if it matches at all.
+    pub fn indices(&self, path: &str) -> Option<Vec<usize>> {
+        self.inner.fuzzy_indices(path, &self.pattern)
+            .map(|(_, indices)| indices)
+    }
+}
+
+impl Matcher for FuzzyMatcher {
+    fn bonus(&self, path: &str) -> Option<f64> {
+        self.score(path).map(|it| it.max(1) as f64)
+    }
+}
@@ -0,0 +1,98 @@
+use std::path::MAIN_SEPARATOR;
+
+use crate::matcher::Matcher;
+
+/// Matches z-style: every keyword must appear in the path, in order,
+/// and the last one must match within the final component.
+///
+/// Matching is smart-case: case-insensitive unless a keyword has an uppercase letter.
+pub struct KeywordMatcher {
+    keywords: Vec<String>,
+    case_sensitive: bool,
+}
+
+impl KeywordMatcher {
+    pub fn new(keywords: &[String]) -> Self {
+        let case_sensitive = keywords.iter().any(|it| it.chars().any(char::is_uppercase));
+        let keywords = keywords.iter()
+            .map(|it| if case_sensitive { it.clone() } else { it.to_lowercase() })
+            .collect();
+        KeywordMatcher { keywords, case_sensitive }
+    }
+}
+
+impl Matcher for KeywordMatcher {
+    /// The final component matching the last keyword exactly weighs 4 times, and as a prefix twice.
+    fn bonus(&self, path: &str) -> Option<f64> {
+        let path = if self.case_sensitive { path.to_string() } else { path.to_lowercase() };
+        let (last, rest) = self.keywords.split_last()?;
+
+        let mut position = 0;
+        for keyword in rest {
+            position += path[position..].find(keyword.as_str())? + keyword.len();
+        }
+
+        let component = path.rfind(MAIN_SEPARATOR).map(|it| it + 1).unwrap_or(0);
+        let found = path.rfind(last.as_str())?;
+        // a keyword spanning several components, e.g. `foo/bar`, must end with the final one.
+        let tail = last.rfind(MAIN_SEPARATOR).map(|it| it + 1).unwrap_or(0);
+        if found < position || found + tail < component {
+            return None;
+        }
+
+        let (component, tail) = (&path[component..], &last[tail..]);
+        if component == tail {
+            Some(4.0)
+        } else if component.starts_with(tail) {
+            Some(2.0)
+        } else {
+            Some(1.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bonus(keywords: &[&str], path: &str) -> Option<f64> {
+        let keywords = keywords.iter().map(|it| it.to_string()).collect::<Vec<_>>();
+        KeywordMatcher::new(&keywords).bonus(path)
+    }
+
+    #[test]
+    fn weighs_the_final_component() {
+        assert_eq!(bonus(&["foo"], "/a/foo"), Some(4.0));
+        assert_eq!(bonus(&["foo"], "/a/foobar"), Some(2.0));
+        assert_eq!(bonus(&["foo"], "/a/barfoo"), Some(1.0));
+        assert_eq!(bonus(&["foo"], "/foo/foo"), Some(4.0));
+    }
+
+    #[test]
+    fn anchors_the_last_keyword_to_the_final_component() {
+        assert_eq!(bonus(&["foo"], "/foo/bar"), None);
+        assert_eq!(bonus(&["foo", "bar"], "/foo/bar"), Some(4.0));
+        assert_eq!(bonus(&["bar", "foo"], "/foo/bar"), None);
+        assert_eq!(bonus(&["foo", "o"], "/a/foo"), None);
+    }
+
+    #[test]
+    fn matches_keywords_spanning_components() {
+        assert_eq!(bonus(&["foo/bar"], "/a/foo/bar"), Some(4.0));
+        assert_eq!(bonus(&["o/ba"], "/a/foo/bar"), Some(2.0));
+        assert_eq!(bonus(&["foo/bar"], "/a/foo/bar/baz"), None);
+        assert_eq!(bonus(&["a", "foo/bar"], "/a/foo/bar"), Some(4.0));
+    }
+
+    #[test]
+    fn matches_smart_case() {
+        assert_eq!(bonus(&["foo"], "/a/Foo"), Some(4.0));
+        assert_eq!(bonus(&["Foo"], "/a/Foo"), Some(4.0));
+        assert_eq!(bonus(&["Foo"], "/a/foo"), None);
+    }
+
+    #[test]
+    fn needs_a_keyword() {
+        assert_eq!(bonus(&[], "/a/foo"), None);
+    }
+}
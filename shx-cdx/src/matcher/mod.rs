@@ -0,0 +1,45 @@
+use crate::history::Entry;
+
+pub mod fuzzy;
+pub mod keyword;
+pub mod pattern;
+
+/// Tells whether a path matches, and how well.
+pub trait Matcher {
+    /// Returns `None` if `path` does not match, or a bonus multiplying its frecency score otherwise.
+    fn bonus(&self, path: &str) -> Option<f64>;
+}
+
+/// Ranks the entries matching by their logical or canonical path by their frecency score times the bonus of the matcher,
+/// learned ones after every visited one.
+/// Entries with the same score are ordered by the shorter path first, then alphabetically.
+pub fn rank<M>(matcher: &M, scored: Vec<(f64, Entry)>) -> Vec<(f64, Entry)>
+where
+    M: Matcher + ?Sized,
+{
+    let mut matched = scored.into_iter()
+        .filter_map(|(score, entry)| bonus(matcher, &entry).map(|bonus| (score * bonus, entry)))
+        .collect::<Vec<_>>();
+    matched.sort_by(|(a, x), (b, y)| {
+        x.is_learned().cmp(&y.is_learned())
+            .then_with(|| b.total_cmp(a))
+            .then_with(|| x.canonical.len().cmp(&y.canonical.len()))
+            .then_with(|| x.canonical.cmp(&y.canonical))
+    });
+    matched
+}
+
+// a directory matches by either of its paths, whichever matches better.
+fn bonus<M>(matcher: &M, entry: &Entry) -> Option<f64>
+where
+    M: Matcher + ?Sized,
+{
+    let canonical = matcher.bonus(&entry.canonical);
+    if entry.logical == entry.canonical {
+        return canonical;
+    }
+    match (matcher.bonus(&entry.logical), canonical) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
@@ -0,0 +1,54 @@
+use globset::{Glob, GlobBuilder, GlobMatcher};
+use regex::Regex;
+
+use crate::matcher::Matcher;
+
+/// Matches paths containing a match of the regex anywhere, e.g. `/-service/tests$/`.
+pub struct RegexMatcher {
+    regex: Regex,
+}
+
+impl RegexMatcher {
+    pub fn new(regex: Regex) -> Self {
+        RegexMatcher { regex }
+    }
+}
+
+impl Matcher for RegexMatcher {
+    fn bonus(&self, path: &str) -> Option<f64> {
+        self.regex.is_match(path).then_some(1.0)
+    }
+}
+
+/// Matches paths ending with the glob, e.g. `*-service/tests`.
+/// `*` and `?` never cross a `/`, while `**` does.
+pub struct PathGlobMatcher {
+    matcher: GlobMatcher,
+}
+
+impl PathGlobMatcher {
+    pub fn new(glob: &Glob) -> Self {
+        PathGlobMatcher { matcher: glob.compile_matcher() }
+    }
+}
+
+impl Matcher for PathGlobMatcher {
+    fn bonus(&self, path: &str) -> Option<f64> {
+        self.matcher.is_match(path).then_some(1.0)
+    }
+}
+
+/// Whether a shortcut should be taken as a glob rather than as keywords.
+pub fn is_glob(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+/// Builds a glob matching paths which end with `pattern`, unless it is absolute.
+pub fn glob(pattern: &str) -> anyhow::Result<Glob> {
+    let pattern = pattern.trim_end_matches('/');
+    let pattern = match pattern.starts_with('/') {
+        true => pattern.to_string(),
+        false => format!("**/{}", pattern),
+    };
+    Ok(GlobBuilder::new(&pattern).literal_separator(true).build()?)
+}
@@ -1,30 +1,294 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+
 use shx_config::cdx::CdxConfig;
 
-use crate::history::History;
+use crate::bookmark::{Bookmark, Bookmarks};
+use crate::cd::CD;
+use crate::cli::DirArgs;
+use crate::crawler::Crawler;
+use crate::error::CdxError;
+use crate::exchange::Format;
+use crate::history::{now, Entry, History, LEARNED};
+use crate::import::Source;
+use crate::listing;
+use crate::listing::Listing;
+use crate::matcher::pattern::{PathGlobMatcher, RegexMatcher};
+use crate::output::{BookmarkRow, OutputFormat, Row};
+use crate::path;
 use crate::path::CanonicalPath;
+use crate::probe::PathState;
+use crate::project::Project;
+use crate::session::Session;
+use crate::shell::{Integration, Shell};
+use crate::theme::color::StyledBridge;
 use crate::theme::formatter::ToPretty;
 use crate::theme::Theme;
 
 pub enum Opts {
-    ShowHistory,
-    Learn(String),
+    ShowHistory(Listing),
+    Learn(String, Option<usize>),
+    Pin(String),
+    Unpin(String),
+    Prune,
+    Mark(String, Option<String>),
+    Unmark(String),
+    Bookmarks,
+    Import(Source, Option<String>, bool),
+    Export(Format, Option<String>),
+    Add(String),
+    Hook(Shell),
+    Init(Shell, Integration),
 }
 
 impl Opts {
-    pub fn show_history(config: CdxConfig, history: History) -> anyhow::Result<String> {
-        let search_size = config.search_size();
+    pub fn show_history(config: CdxConfig, history: History, listing: Listing, format: Option<OutputFormat>) -> anyhow::Result<String> {
+        let size = match listing.all {
+            true => usize::MAX,
+            false => listing.limit.unwrap_or(config.search_size()),
+        };
+        let project = match listing.project {
+            true => match Project::current(&config.project_markers()) {
+                Some(project) => Some(project),
+                None => bail!("[error] {} is not within a project", path::logical(Path::new(".")).display()),
+            },
+            false => None,
+        };
+        let under = listing.under.as_ref().map(CanonicalPath::from_string).transpose()?;
+
+        let mut scored = history.scored(usize::MAX)
+            .into_iter()
+            .filter(|(_, entry)| listing.accepts(entry, under.as_ref(), project.as_ref()))
+            .collect::<Vec<_>>();
+        listing.sort(&mut scored);
+
+        let keep = |state: &PathState| !listing.existing || *state == PathState::Present;
+        let listed = match listing.sort {
+            // an order given explicitly is kept, rather than putting the directories which cannot be found last.
+            Some(_) => scored.into_iter()
+                .map(|(score, entry)| {
+                    let state = history.probe(&entry);
+                    (score, entry, state)
+                })
+                .filter(|(_, _, state)| keep(state))
+                .take(size)
+                .collect(),
+            None => history.probe_ranked(scored, size)
+                .into_iter()
+                .filter(|(_, _, state)| keep(state))
+                .collect(),
+        };
+        Self::list(listed, format)
+    }
 
-        let output = history.read(search_size)
-            .iter()
+    /// Lists the directories `dir` would jump to, best first, without jumping nor recording anything.
+    pub fn query(config: CdxConfig, history: History, dir: DirArgs, format: Option<OutputFormat>) -> anyhow::Result<String> {
+        let matched = match dir {
+            DirArgs::Shortcut(keywords) => CD::candidates(&config, &history, &keywords, false)?,
+            DirArgs::Scoped(keywords) => CD::candidates(&config, &history, &keywords, true)?,
+            DirArgs::Regex(regex) => CD::pattern_candidates(&config, &history, &RegexMatcher::new(regex))?,
+            DirArgs::Glob(glob) => CD::pattern_candidates(&config, &history, &PathGlobMatcher::new(&glob))?,
+            _ => bail!("[error] only shortcuts, regexes and globs can be queried"),
+        };
+        Self::list(history.probe_ranked(matched, config.search_size()), format)
+    }
+
+    // lists the probed directories as text, or for scripts in the given format.
+    fn list(listed: Vec<(f64, Entry, PathState)>, format: Option<OutputFormat>) -> anyhow::Result<String> {
+        if let Some(format) = format.filter(OutputFormat::is_structured) {
+            let rows = listed.iter()
+                .enumerate()
+                .map(|(index, (score, entry, state))| Row::new(index, *score, entry, *state))
+                .collect::<Vec<_>>();
+            return format.render(&rows);
+        }
+
+        let now = now();
+        let output = listed.iter()
             .enumerate()
-            .map(|(index, entry)| entry.prettify(index, &Theme::default()))
+            .map(|(index, (score, entry, state))| entry.prettify(index, &Theme::default())
+                .with_visits(&listing::ago(entry.last_access, now), entry.count, *score)
+                .with_note(state.note()))
             .map(|it| it.to_string())
             .collect::<Vec<_>>()
             .join("\n");
         Ok(output)
     }
 
-    pub fn learn(dir: String) -> anyhow::Result<String> {
-        todo!()
+    pub fn pin(mut history: History, dir: String, pinned: bool) -> anyhow::Result<String> {
+        let path = CanonicalPath::from_string(&dir)?;
+        history.pin(Entry::new(&dir, &path).with_logical(path.logical()), pinned);
+        history.save()?;
+        Ok(path.to_string())
+    }
+
+    /// Removes the directories which no longer exist. Those on a filesystem which is not mounted are kept,
+    /// as well as those which may not be entered, e.g. for want of a Kerberos ticket, and pinned ones. Entries recorded before their filesystem was, which merely seem to be
+    /// on an unmounted volume, are kept for `prune_grace_days` since their last visit.
+    pub fn prune(config: CdxConfig, mut history: History) -> anyhow::Result<String> {
+        let grace_period = config.prune_grace_days() * 24 * 60 * 60;
+        let now = now();
+
+        let (mut pruned, mut kept) = (0, 0);
+        for entry in history.entries().to_vec() {
+            let expired = now.saturating_sub(entry.last_access) > grace_period;
+            let dead = match history.probe(&entry) {
+                PathState::Present => continue,
+                PathState::Missing => !entry.pinned,
+                PathState::Denied => false,
+                PathState::Unavailable => !entry.pinned && entry.mount.is_empty() && expired,
+            };
+            if dead {
+                history.remove(entry);
+                pruned += 1;
+            } else {
+                kept += 1;
+            }
+        }
+        history.save()?;
+        Session::remove_stale(Duration::from_secs(grace_period))?;
+        Ok(format!("pruned {} directories ({} unavailable, denied or pinned kept)", pruned, kept))
+    }
+
+    pub fn mark(name: String, dir: Option<String>) -> anyhow::Result<String> {
+        let path = CanonicalPath::from_string(&dir.unwrap_or(".".to_string()))?;
+        let mut bookmarks = Bookmarks::open()?;
+        bookmarks.mark(name.clone(), Bookmark {
+            canonical: path.to_string(),
+            logical: path.logical().display().to_string(),
+        })?;
+        bookmarks.save()?;
+        Ok(format!("marked @{} as {}", name, path.logical().display()))
+    }
+
+    pub fn unmark(name: String) -> anyhow::Result<String> {
+        let mut bookmarks = Bookmarks::open()?;
+        let Some(bookmark) = bookmarks.unmark(&name) else {
+            bail!(CdxError::NoMatch(format!("[error] no bookmark named `{}`", name)));
+        };
+        bookmarks.save()?;
+        Ok(format!("unmarked @{} ({})", name, bookmark.logical))
+    }
+
+    pub fn bookmarks(history: History, format: Option<OutputFormat>) -> anyhow::Result<String> {
+        let bookmarks = Bookmarks::open()?;
+        if let Some(format) = format.filter(OutputFormat::is_structured) {
+            let rows = bookmarks.iter()
+                .map(|(name, bookmark)| BookmarkRow {
+                    name: name.clone(),
+                    canonical: bookmark.canonical.clone(),
+                    logical: bookmark.logical.clone(),
+                    state: history.probe(&Entry::new("", &bookmark.canonical)).note().unwrap_or("present"),
+                })
+                .collect::<Vec<_>>();
+            return format.render(&rows);
+        }
+
+        let theme = Theme::default();
+        let output = bookmarks.iter()
+            .map(|(name, bookmark)| {
+                let name = StyledBridge::new(format!("@{}", name), theme.index()).colorize();
+                let path = StyledBridge::new(bookmark.logical.clone(), theme.canonical()).colorize();
+                let state = history.probe(&Entry::new("", &bookmark.canonical));
+                match state.note() {
+                    Some(note) => format!("{}: {} {}", name, path, StyledBridge::new(format!("({})", note), theme.highlight()).colorize()),
+                    None => format!("{}: {}", name, path),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(output)
+    }
+
+    pub fn import(mut history: History, source: Source, path: Option<String>, replace: bool) -> anyhow::Result<String> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => source.default_path()?,
+        };
+        let entries = source.read(&path)?;
+        if replace {
+            history.clear();
+        }
+
+        let total = entries.len();
+        // directories which no longer exist cannot be canonicalized, nor jumped to.
+        let existing = entries.into_iter()
+            .filter_map(|entry| {
+                let canonical = CanonicalPath::from_string(&entry.canonical).ok()?;
+                Some(Entry { canonical: canonical.to_string(), ..entry })
+            })
+            .collect::<Vec<_>>();
+        let skipped = total - existing.len();
+        let aged = existing.len() - history.merge(existing);
+        history.save()?;
+        Ok(format!("imported {} directories from {} ({} skipped, {} aged out)", total - skipped - aged, path.display(), skipped, aged))
+    }
+
+    /// Records a visit to `dir` without jumping to it. Prints nothing, so that it can run in a prompt hook.
+    pub fn add(mut history: History, dir: String) -> anyhow::Result<String> {
+        let path = CanonicalPath::from_string(&dir)?;
+        // `cdx` itself has just recorded the directory it changed to, which triggered the hook.
+        let already_recorded = history.recent(1)
+            .first()
+            .is_some_and(|it| it.canonical == path.to_string());
+        if !already_recorded {
+            history.append_last(Entry::new(&dir, &path).with_logical(path.logical()));
+            history.save()?;
+        }
+        if let Some(mut session) = Session::current()? {
+            session.visit(path.logical());
+            session.save()?;
+        }
+        Ok(String::new())
+    }
+
+    pub fn hook(shell: Shell) -> anyhow::Result<String> {
+        let bin = std::env::current_exe()?;
+        Ok(shell.hook(&bin.display().to_string()))
+    }
+
+    pub fn init(shell: Shell, integration: Integration) -> anyhow::Result<String> {
+        let bin = std::env::current_exe()?;
+        Ok(shell.init(&bin.display().to_string(), &integration))
+    }
+
+    pub fn export(history: History, format: Format, output: Option<String>) -> anyhow::Result<String> {
+        let exported = format.export(history.entries())?;
+        match output {
+            None => Ok(exported),
+            Some(output) => {
+                fs::write(&output, exported)
+                    .with_context(|| format!("[error] cannot write to {}", output))?;
+                Ok(format!("exported {} directories to {}", history.entries().len(), output))
+            }
+        }
+    }
+
+    /// Seeds the history with the directories under `dir`. They rank after any actual visit until visited,
+    /// and are scored as if last visited four half-lives ago. Project roots count twice.
+    pub fn learn(config: CdxConfig, mut history: History, dir: String, depth: Option<usize>) -> anyhow::Result<String> {
+        let root = CanonicalPath::from_string(&dir)?;
+        let last_access = now().saturating_sub(4 * config.frecency_half_life() * 60 * 60);
+
+        let discovered = Crawler::new(&config)
+            .with_depth(depth)
+            .crawl(root.as_ref());
+        let projects = discovered.iter().filter(|it| it.project).count();
+        let learned = history.learn(discovered.iter().map(|it| {
+            // the crawler walks the canonical tree, which sits under the logical root, too.
+            let logical = it.path.strip_prefix(&root)
+                .map(|relative| root.logical().join(relative))
+                .unwrap_or_else(|_| it.path.clone());
+            Entry {
+                count: if it.project { 2 } else { 1 },
+                last_access,
+                ..Entry::new(LEARNED, &it.path).with_logical(logical)
+            }
+        }));
+        history.save()?;
+        Ok(format!("learned {} directories under {} ({} projects)", learned, root, projects))
     }
 }
\ No newline at end of file
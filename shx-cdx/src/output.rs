@@ -0,0 +1,108 @@
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::error::CdxError;
+use crate::history::Entry;
+use crate::probe::PathState;
+
+/// Formats listings and errors can be printed in for scripts, instead of colored text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// A single JSON array.
+    Json,
+    /// One JSON object per line.
+    Jsonl,
+    /// One line per row, with tab-separated columns.
+    Tsv,
+    /// The usual text, without colors.
+    Plain,
+}
+
+/// A directory of a listing, as scripts read it.
+#[derive(Serialize)]
+pub struct Row {
+    pub index: usize,
+    pub canonical: String,
+    pub logical: String,
+    pub raw: String,
+    /// Seconds since the unix epoch.
+    pub last_access: u64,
+    pub count: u32,
+    pub score: f64,
+    pub pinned: bool,
+    /// `present`, `missing`, `unavailable` or `denied`.
+    pub state: &'static str,
+}
+
+impl Row {
+    pub fn new(index: usize, score: f64, entry: &Entry, state: PathState) -> Self {
+        Row {
+            index,
+            canonical: entry.canonical.clone(),
+            logical: entry.logical.clone(),
+            raw: entry.raw.clone(),
+            last_access: entry.last_access,
+            count: entry.count,
+            score,
+            pinned: entry.pinned,
+            state: state.note().unwrap_or("present"),
+        }
+    }
+}
+
+/// A bookmark of a listing, as scripts read it.
+#[derive(Serialize)]
+pub struct BookmarkRow {
+    pub name: String,
+    pub canonical: String,
+    pub logical: String,
+    /// `present`, `missing`, `unavailable` or `denied`.
+    pub state: &'static str,
+}
+
+impl OutputFormat {
+    /// Whether the output is meant for scripts rather than humans, i.e. rendered by `render` rather than as text.
+    pub fn is_structured(&self) -> bool {
+        *self != OutputFormat::Plain
+    }
+
+    pub fn render<T: Serialize>(&self, rows: &[T]) -> anyhow::Result<String> {
+        let output = match self {
+            OutputFormat::Json => serde_json::to_string(rows)?,
+            OutputFormat::Jsonl => rows.iter()
+                .map(serde_json::to_string)
+                .collect::<Result<Vec<_>, _>>()?
+                .join("\n"),
+            OutputFormat::Tsv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .delimiter(b'\t')
+                    .has_headers(false)
+                    .from_writer(Vec::new());
+                for row in rows {
+                    writer.serialize(row)?;
+                }
+                String::from_utf8(writer.into_inner()?)?.trim_end().to_string()
+            }
+            OutputFormat::Plain => unreachable!("plain output is rendered as text"),
+        };
+        Ok(output)
+    }
+
+    /// Renders an error for stderr: a JSON object with its kind and exit code for JSON formats, as text otherwise.
+    pub fn error(&self, error: &anyhow::Error) -> String {
+        let message = format!("{:#}", error);
+        if !matches!(self, OutputFormat::Json | OutputFormat::Jsonl) {
+            return message;
+        }
+        let cdx_error = error.chain().find_map(|it| it.downcast_ref::<CdxError>());
+        let mut json = serde_json::json!({
+            "kind": cdx_error.map_or("other", CdxError::kind),
+            "code": CdxError::exit_code_of(error),
+            "message": message,
+        });
+        if let Some(CdxError::Ambiguous(_, candidates)) = cdx_error {
+            json["candidates"] = serde_json::json!(candidates);
+        }
+        json.to_string()
+    }
+}
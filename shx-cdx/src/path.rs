@@ -1,8 +1,14 @@
 use std::fmt::{Display, Formatter};
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::bail;
 
 /// Represents a directory path, which is guaranteed to be a canonicalized directory.
-pub struct CanonicalPath(PathBuf);
+/// Keeps the logical path it was reached by, too, with its symlinks unresolved.
+pub struct CanonicalPath {
+    canonical: PathBuf,
+    logical: PathBuf,
+}
 
 impl CanonicalPath {
     pub fn from_string(path: &String) -> anyhow::Result<Self> {
@@ -10,12 +16,56 @@ impl CanonicalPath {
     }
 
     pub fn from_path(path: PathBuf) -> anyhow::Result<Self> {
-        Ok(Self(path.canonicalize()?))
+        let logical = logical(&path);
+        let canonical = logical.canonicalize().or_else(|_| path.canonicalize())?;
+        if !canonical.is_dir() {
+            bail!("[error] {} is not a directory", path.display());
+        }
+        Ok(Self { canonical, logical })
+    }
+
+    pub fn logical(&self) -> &Path {
+        &self.logical
+    }
+}
+
+impl AsRef<Path> for CanonicalPath {
+    fn as_ref(&self) -> &Path {
+        &self.canonical
     }
 }
 
 impl Display for CanonicalPath {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0.display())
+        write!(f, "{}", self.canonical.display())
     }
-}
\ No newline at end of file
+}
+
+/// The absolute path of `path` as `cd -L` sees it: relative to `$PWD`,
+/// with `.` and `..` resolved lexically rather than by following symlinks.
+pub fn logical(path: &Path) -> PathBuf {
+    let absolute = match path.is_absolute() {
+        true => path.to_path_buf(),
+        false => working_dir().join(path),
+    };
+    absolute.components()
+        .fold(PathBuf::new(), |mut logical, component| {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    logical.pop();
+                }
+                it => logical.push(it),
+            }
+            logical
+        })
+}
+
+// `$PWD` keeps the symlinks the shell went through, as long as it still denotes the working directory.
+fn working_dir() -> PathBuf {
+    let physical = std::env::current_dir().unwrap_or_default();
+    match std::env::var_os("PWD").map(PathBuf::from) {
+        Some(pwd) if pwd.is_absolute() && pwd.canonicalize().ok() == physical.canonicalize().ok() => pwd,
+        _ => physical,
+    }
+}
@@ -0,0 +1,172 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use shx_config::cdx::CdxConfig;
+
+use crate::history::Entry;
+
+/// Where removable and network volumes are usually mounted.
+const MOUNT_ROOTS: [&str; 4] = ["/media", "/mnt", "/run/media", "/Volumes"];
+
+/// Whether a directory of the history can still be changed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathState {
+    Present,
+    /// The directory has been removed.
+    Missing,
+    /// The directory cannot be reached for now, because its filesystem is not mounted or does not answer.
+    Unavailable,
+    /// The directory, or one of its ancestors, may not be entered.
+    Denied,
+}
+
+impl PathState {
+    /// How listings mark directories which cannot be changed to.
+    pub fn note(&self) -> Option<&'static str> {
+        match self {
+            PathState::Present => None,
+            PathState::Missing => Some("missing"),
+            PathState::Unavailable => Some("unavailable"),
+            PathState::Denied => Some("denied"),
+        }
+    }
+}
+
+/// Checks whether directories exist, without hanging on unresponsive network filesystems.
+pub struct Prober {
+    /// The mount points of the system, if they can be listed.
+    mounts: Option<Vec<PathBuf>>,
+    timeout: Duration,
+    /// Mount points which did not answer in time, so that their other directories are not waited for again,
+    /// or the directories themselves when their filesystem is unknown.
+    hung: RefCell<HashSet<PathBuf>>,
+}
+
+impl Prober {
+    pub fn new(config: &CdxConfig) -> Self {
+        Prober {
+            mounts: mounts(),
+            timeout: Duration::from_millis(config.stat_timeout_ms()),
+            hung: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// The mount point of the filesystem `path` lives on, or an empty string if unknown.
+    pub fn mount_of(&self, path: &Path) -> String {
+        self.mounts.iter()
+            .flatten()
+            .filter(|it| path.starts_with(it))
+            .max_by_key(|it| it.as_os_str().len())
+            .map(|it| it.display().to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn probe(&self, entry: &Entry) -> PathState {
+        let path = Path::new(&entry.canonical);
+        let mount = match entry.mount.is_empty() {
+            true => PathBuf::from(self.mount_of(path)),
+            false => PathBuf::from(&entry.mount),
+        };
+        let unmounted = self.mounts.as_ref()
+            .is_some_and(|it| !entry.mount.is_empty() && !it.contains(&mount));
+        // without knowing the filesystem, only the directory itself is known to hang.
+        let hung = match mount.as_os_str().is_empty() {
+            true => path.to_path_buf(),
+            false => mount,
+        };
+        if unmounted || self.hung.borrow().contains(&hung) {
+            return PathState::Unavailable;
+        }
+
+        match self.metadata(path) {
+            None => {
+                self.hung.borrow_mut().insert(hung);
+                PathState::Unavailable
+            }
+            Some(Ok(metadata)) if metadata.is_dir() => PathState::Present,
+            Some(Ok(_)) => PathState::Missing,
+            // without a recorded filesystem, an unmounted volume can only be guessed.
+            Some(Err(e)) if e.kind() == ErrorKind::NotFound => match entry.mount.is_empty() && on_unmounted_volume(path) {
+                true => PathState::Unavailable,
+                false => PathState::Missing,
+            },
+            Some(Err(e)) if e.kind() == ErrorKind::PermissionDenied => PathState::Denied,
+            Some(Err(e)) if is_stale_mount(&e) => PathState::Unavailable,
+            Some(Err(_)) => PathState::Missing,
+        }
+    }
+
+    // stats on another thread, which is left behind if the filesystem hangs.
+    fn metadata(&self, path: &Path) -> Option<std::io::Result<fs::Metadata>> {
+        let (sender, receiver) = mpsc::channel();
+        let path = path.to_path_buf();
+        thread::spawn(move || {
+            // nobody listens anymore once timed out.
+            let _ = sender.send(fs::metadata(path));
+        });
+        receiver.recv_timeout(self.timeout).ok()
+    }
+}
+
+// errors of a network mount whose server went away, rather than of the directory itself.
+fn is_stale_mount(e: &std::io::Error) -> bool {
+    const EIO: i32 = 5;
+    matches!(e.kind(), ErrorKind::StaleNetworkFileHandle | ErrorKind::NotConnected) || e.raw_os_error() == Some(EIO)
+}
+
+// an unmounted volume leaves either nothing or an empty mount point behind.
+fn on_unmounted_volume(path: &Path) -> bool {
+    if MOUNT_ROOTS.iter().any(|it| path.starts_with(it)) {
+        return true;
+    }
+    path.ancestors()
+        .skip(1)
+        .find(|it| it.exists())
+        .filter(|it| it.parent().is_some())
+        .and_then(|it| fs::read_dir(it).ok())
+        .is_some_and(|mut it| it.next().is_none())
+}
+
+// each line of `/proc/self/mounts` is `<device> <mount point> <type> <options> 0 0`,
+// where whitespace within the mount point is escaped in octal, e.g. `\040`.
+#[cfg(target_os = "linux")]
+fn mounts() -> Option<Vec<PathBuf>> {
+    let content = fs::read_to_string("/proc/self/mounts").ok()?;
+    Some(content.lines()
+        .filter_map(|it| it.split(' ').nth(1))
+        .map(|it| PathBuf::from(unescape(it)))
+        .collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn mounts() -> Option<Vec<PathBuf>> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::new();
+    let mut rest = s;
+    while let Some(index) = rest.find('\\') {
+        unescaped.push_str(&rest[..index]);
+        let code = rest.get(index + 1..index + 4).and_then(|it| u8::from_str_radix(it, 8).ok());
+        match code {
+            Some(code) => {
+                unescaped.push(code as char);
+                rest = &rest[index + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use crate::history::Entry;
+use crate::path;
+use crate::path::CanonicalPath;
+
+/// Directories marking the root of a repository, which scopes a project before any other marker.
+const VCS_MARKERS: [&str; 4] = [".git", ".hg", ".svn", ".jj"];
+
+/// Whether `dir` is the root of a project, i.e. it holds any of the `markers`.
+pub fn is_project_root(dir: &Path, markers: &[String]) -> bool {
+    markers.iter().any(|it| dir.join(it).exists())
+}
+
+/// The nearest project root holding `dir`, which may be `dir` itself.
+fn find_root<'a>(dir: &'a Path, markers: &[String]) -> Option<&'a Path> {
+    dir.ancestors().find(|it| is_project_root(it, markers))
+}
+
+/// The project the current directory belongs to, which history lookups can be scoped to.
+pub struct Project {
+    root: CanonicalPath,
+}
+
+impl Project {
+    /// The repository holding the current directory, or else the nearest directory holding any of the `markers`,
+    /// so that a project of a monorepo scopes the whole monorepo.
+    pub fn current(markers: &[String]) -> Option<Self> {
+        let current = path::logical(Path::new("."));
+        let vcs = VCS_MARKERS.map(String::from);
+        let root = find_root(&current, &vcs).or_else(|| find_root(&current, markers))?;
+        CanonicalPath::from_path(root.to_path_buf()).ok().map(|root| Project { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        self.root.logical()
+    }
+
+    /// Whether the directory of `entry` lies within the project, by either of its paths.
+    pub fn contains(&self, entry: &Entry) -> bool {
+        Path::new(&entry.canonical).starts_with(&self.root) || Path::new(&entry.logical).starts_with(self.root.logical())
+    }
+}
@@ -0,0 +1,240 @@
+use anyhow::bail;
+use bincode::Options;
+use serde::Deserialize;
+
+use crate::history::{now, Entry};
+
+/// Marks a `cdx.db` written with a header. Databases without it predate versioning.
+const MAGIC: &[u8; 4] = b"CDXD";
+
+/// The schema version of `Entry`. Bump it whenever a field is added,
+/// and freeze the previous layout below together with a migration to the next one.
+pub const VERSION: u32 = 4;
+
+/// Serializes entries in the current schema, prefixed by the header.
+pub fn encode(entries: &[Entry]) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::from(*MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend(bincode::options().serialize(entries)?);
+    Ok(bytes)
+}
+
+/// Deserializes entries of any known schema, migrating them to the current one.
+/// Returns the version the database was written in, too.
+pub fn decode(bytes: &[u8]) -> anyhow::Result<(Vec<Entry>, u32)> {
+    let Some(rest) = bytes.strip_prefix(MAGIC) else {
+        return decode_headerless(bytes);
+    };
+    if rest.len() < 4 {
+        bail!("truncated header");
+    }
+    let (version, payload) = rest.split_at(4);
+    let version = u32::from_le_bytes(version.try_into()?);
+    if version > VERSION {
+        bail!("schema version {} is newer than the supported version {}", version, VERSION);
+    }
+    Ok((migrate(version, payload)?, version))
+}
+
+/// Whether the database was written in a schema newer than this binary understands.
+pub fn is_newer(bytes: &[u8]) -> bool {
+    bytes.strip_prefix(MAGIC)
+        .and_then(|it| it.get(..4))
+        .and_then(|it| it.try_into().ok())
+        .is_some_and(|it| u32::from_le_bytes(it) > VERSION)
+}
+
+fn migrate(version: u32, payload: &[u8]) -> anyhow::Result<Vec<Entry>> {
+    let deserializer = bincode::options();
+    let entries = match version {
+        0 => migrate_v1(upgrade(deserializer.deserialize::<Vec<EntryV0>>(payload)?)),
+        1 => migrate_v1(deserializer.deserialize::<Vec<EntryV1>>(payload)?),
+        2 => upgrade(upgrade::<_, EntryV3>(deserializer.deserialize::<Vec<EntryV2>>(payload)?)),
+        3 => upgrade(deserializer.deserialize::<Vec<EntryV3>>(payload)?),
+        _ => deserializer.deserialize::<Vec<Entry>>(payload)?,
+    };
+    Ok(entries)
+}
+
+fn migrate_v1(entries: Vec<EntryV1>) -> Vec<Entry> {
+    upgrade(upgrade::<_, EntryV3>(upgrade::<_, EntryV2>(entries)))
+}
+
+// only the original layout was ever written without the header, so nothing else is guessed,
+// lest another layout happen to decode a real database into garbage which then gets saved over it.
+fn decode_headerless(bytes: &[u8]) -> anyhow::Result<(Vec<Entry>, u32)> {
+    migrate(0, bytes)
+        .map(|it| (it, 0))
+        .map_err(|e| anyhow::anyhow!("unknown database format: {}", e))
+}
+
+fn upgrade<A, B>(entries: Vec<A>) -> Vec<B>
+where
+    A: Into<B>,
+{
+    entries.into_iter().map(Into::into).collect()
+}
+
+/// The original layout, without frecency.
+#[derive(Deserialize)]
+struct EntryV0 {
+    raw: String,
+    canonical: String,
+}
+
+/// Added `count` and `last_access`.
+#[derive(Deserialize)]
+struct EntryV1 {
+    raw: String,
+    canonical: String,
+    count: u32,
+    last_access: u64,
+}
+
+impl From<EntryV0> for EntryV1 {
+    fn from(it: EntryV0) -> Self {
+        EntryV1 { raw: it.raw, canonical: it.canonical, count: 1, last_access: now() }
+    }
+}
+
+/// Added `pinned`.
+#[derive(Deserialize)]
+struct EntryV2 {
+    raw: String,
+    canonical: String,
+    count: u32,
+    last_access: u64,
+    pinned: bool,
+}
+
+impl From<EntryV1> for EntryV2 {
+    fn from(it: EntryV1) -> Self {
+        EntryV2 { raw: it.raw, canonical: it.canonical, count: it.count, last_access: it.last_access, pinned: false }
+    }
+}
+
+/// Added `logical`, which is the canonical path for entries recorded before.
+#[derive(Deserialize)]
+struct EntryV3 {
+    raw: String,
+    canonical: String,
+    logical: String,
+    count: u32,
+    last_access: u64,
+    pinned: bool,
+}
+
+impl From<EntryV2> for EntryV3 {
+    fn from(it: EntryV2) -> Self {
+        EntryV3 {
+            raw: it.raw,
+            logical: it.canonical.clone(),
+            canonical: it.canonical,
+            count: it.count,
+            last_access: it.last_access,
+            pinned: it.pinned,
+        }
+    }
+}
+
+/// Added `mount`, which stays unknown for entries recorded before until they are visited again.
+impl From<EntryV3> for Entry {
+    fn from(it: EntryV3) -> Self {
+        Entry {
+            raw: it.raw,
+            canonical: it.canonical,
+            logical: it.logical,
+            mount: String::new(),
+            count: it.count,
+            last_access: it.last_access,
+            pinned: it.pinned,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    /// `Entry` as the baseline wrote it, without any header.
+    #[derive(Serialize)]
+    struct BaselineEntry {
+        raw: String,
+        canonical: String,
+    }
+
+    fn baseline(entries: &[(&str, &str)]) -> Vec<u8> {
+        let entries = entries.iter()
+            .map(|(raw, canonical)| BaselineEntry { raw: raw.to_string(), canonical: canonical.to_string() })
+            .collect::<Vec<_>>();
+        bincode::options().serialize(&entries).unwrap()
+    }
+
+    #[test]
+    fn decodes_baseline_database() {
+        let bytes = baseline(&[(":foo", "/home/me/foo"), ("bar", "/home/me/bar")]);
+
+        let (entries, version) = decode(&bytes).unwrap();
+
+        assert_eq!(version, 0);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].raw, ":foo");
+        assert_eq!(entries[0].canonical, "/home/me/foo");
+        assert_eq!(entries[0].logical, "/home/me/foo");
+        assert_eq!(entries[0].mount, "");
+        assert_eq!(entries[0].count, 1);
+        assert!(!entries[0].pinned);
+        assert_eq!(entries[1].raw, "bar");
+        assert_eq!(entries[1].canonical, "/home/me/bar");
+    }
+
+    #[test]
+    fn decodes_empty_baseline_database() {
+        let (entries, version) = decode(&baseline(&[])).unwrap();
+
+        assert!(entries.is_empty());
+        assert_eq!(version, 0);
+    }
+
+    #[test]
+    fn round_trips_baseline_database() {
+        let (entries, _) = decode(&baseline(&[(":foo", "/home/me/foo")])).unwrap();
+
+        let (decoded, version) = decode(&encode(&entries).unwrap()).unwrap();
+
+        assert_eq!(version, VERSION);
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].raw, ":foo");
+        assert_eq!(decoded[0].canonical, "/home/me/foo");
+        assert_eq!(decoded[0].count, entries[0].count);
+        assert_eq!(decoded[0].last_access, entries[0].last_access);
+    }
+
+    #[test]
+    fn rejects_headerless_database_of_another_layout() {
+        let entries = vec![Entry::new(":foo", "/home/me/foo")];
+        let bytes = bincode::options().serialize(&entries).unwrap();
+
+        assert!(decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_newer_database() {
+        let mut bytes = Vec::from(*MAGIC);
+        bytes.extend_from_slice(&(VERSION + 1).to_le_bytes());
+        bytes.extend(baseline(&[(":foo", "/home/me/foo")]));
+
+        assert!(is_newer(&bytes));
+        assert!(decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn current_database_is_not_newer() {
+        let bytes = encode(&[Entry::new(":foo", "/home/me/foo")]).unwrap();
+
+        assert!(!is_newer(&bytes));
+        assert!(!is_newer(&baseline(&[(":foo", "/home/me/foo")])));
+    }
+}
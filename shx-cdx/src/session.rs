@@ -0,0 +1,123 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use shx_config::config::path_for;
+
+use crate::error::CdxError;
+use crate::path;
+
+/// Set by the shell integration to tell shells apart.
+pub const SESSION: &str = "SHX_CDX_SESSION";
+const SESSIONS: &str = "cdx-sessions";
+/// How many directories a session remembers to go back to.
+const STACK_SIZE: usize = 256;
+
+/// The directories a single shell went through, to go back and forth like a browser,
+/// backed by a file under `cdx-sessions`.
+pub struct Session {
+    path: PathBuf,
+    stack: Stack,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Stack {
+    dirs: Vec<String>,
+    /// Index of the directory the shell is in.
+    cursor: usize,
+}
+
+impl Session {
+    /// The session of the calling shell, if its integration exported `SHX_CDX_SESSION`.
+    pub fn current() -> anyhow::Result<Option<Self>> {
+        let id = match std::env::var(SESSION) {
+            Ok(id) if !id.is_empty() => id,
+            _ => return Ok(None),
+        };
+        // the id names a file, so it must not escape the sessions directory.
+        let id = id.replace(|it: char| !it.is_ascii_alphanumeric() && it != '-' && it != '.', "_");
+        let path = path_for(SESSIONS)?.join(id);
+        let stack = match fs::read_to_string(&path) {
+            // a session is disposable, so a broken one merely starts over.
+            Ok(content) => toml::from_str(&content).unwrap_or_default(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Stack::default(),
+            Err(e) => {
+                let e = anyhow::Error::from(e).context(format!("[fatal] cannot read session: {}", path.display()));
+                return Err(CdxError::Database(e).into());
+            }
+        };
+        Ok(Some(Session { path, stack }))
+    }
+
+    /// Moves to `dir`, dropping the directories gone back from.
+    /// The first move of a session remembers where the shell started from, too.
+    pub fn visit(&mut self, dir: &Path) {
+        let stack = &mut self.stack;
+        let dir = dir.display().to_string();
+        if stack.dirs.get(stack.cursor) == Some(&dir) {
+            return;
+        }
+        if stack.dirs.is_empty() {
+            stack.dirs.push(path::logical(Path::new(".")).display().to_string());
+        }
+        stack.dirs.truncate(stack.cursor + 1);
+        stack.dirs.push(dir);
+        if stack.dirs.len() > STACK_SIZE {
+            stack.dirs.drain(..stack.dirs.len() - STACK_SIZE);
+        }
+        stack.cursor = stack.dirs.len() - 1;
+    }
+
+    /// Moves `offset` directories back, or forth if positive, stopping at either end.
+    /// Returns `None` if there is nowhere to move.
+    pub fn step(&mut self, offset: isize) -> Option<PathBuf> {
+        let stack = &mut self.stack;
+        let last = stack.dirs.len().checked_sub(1)?;
+        let cursor = stack.cursor.saturating_add_signed(offset).min(last);
+        if cursor == stack.cursor {
+            return None;
+        }
+        stack.cursor = cursor;
+        stack.dirs.get(cursor).map(PathBuf::from)
+    }
+
+    /// The directory the shell was in before the current one.
+    pub fn previous(&self) -> Option<PathBuf> {
+        let cursor = self.stack.cursor.checked_sub(1)?;
+        self.stack.dirs.get(cursor).map(PathBuf::from)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        self.write().map_err(|e| CdxError::Database(e).into())
+    }
+
+    fn write(&self) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, toml::to_string(&self.stack)?)
+            .with_context(|| format!("[fatal] cannot write session: {}", self.path.display()))
+    }
+
+    /// Removes the sessions untouched for `age`, whose shells are most likely gone.
+    pub fn remove_stale(age: Duration) -> anyhow::Result<usize> {
+        let dir = path_for(SESSIONS)?;
+        let Ok(sessions) = fs::read_dir(&dir) else {
+            return Ok(0);
+        };
+        let now = SystemTime::now();
+        let mut removed = 0;
+        for session in sessions.filter_map(Result::ok) {
+            let stale = session.metadata()
+                .and_then(|it| it.modified())
+                .is_ok_and(|it| now.duration_since(it).unwrap_or_default() > age);
+            if stale && fs::remove_file(session.path()).is_ok() {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
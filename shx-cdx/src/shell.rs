@@ -0,0 +1,208 @@
+use clap::ValueEnum;
+
+use crate::session::SESSION;
+
+/// Shells `cdx` can generate integration code for.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Nushell,
+    Powershell,
+    Elvish,
+}
+
+/// What `init` should define besides the jumping function.
+#[derive(Debug, Clone)]
+pub struct Integration {
+    /// Name of the jumping function, e.g. `cdx`, `j` or `z`.
+    pub cmd: String,
+    /// Whether `cd` is replaced by the jumping function, too.
+    pub wrap_cd: bool,
+    /// Whether the prompt hook is installed, too.
+    pub hook: bool,
+}
+
+impl Shell {
+    /// The full integration: a function which runs `bin` and changes to the directory it prints,
+    /// passing options through as they are, plus whatever `integration` asks for on top of it.
+    /// `bin` prints only the destination to stdout, and its diagnostics go straight to stderr.
+    pub fn init(&self, bin: &str, integration: &Integration) -> String {
+        let mut script = self.session();
+        script.push_str(&self.function(&self.quote(bin), &integration.cmd));
+        if integration.wrap_cd {
+            script.push_str(&self.wrap_cd(&integration.cmd));
+        }
+        if integration.hook {
+            script.push_str(&self.hook(bin));
+        }
+        script
+    }
+
+    fn function(&self, bin: &str, cmd: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!(r#"{cmd}() {{
+    case "${{1:-}}" in
+        --)
+            shift
+            ;;
+        -?*)
+            {bin} "$@"
+            return
+            ;;
+    esac
+
+    local output
+    output="$({bin} -- "$@")" || return
+    [ -n "$output" ] && builtin cd -- "$output"
+}}
+"#),
+            Shell::Fish => format!(r#"function {cmd}
+    if set -q argv[1]; and test "$argv[1]" = '--'
+        set -e argv[1]
+    else if set -q argv[1]; and string match -q -- '-?*' $argv[1]
+        {bin} $argv
+        return
+    end
+
+    set -l output ({bin} -- $argv); or return
+    test -n "$output"; and builtin cd -- $output
+end
+"#),
+            Shell::Nushell => format!(r#"def --env --wrapped {cmd} [...rest: string] {{
+    let rest = if ($rest | is-not-empty) and $rest.0 == '--' {{ $rest | skip 1 }} else {{ $rest }}
+    if ($rest | is-not-empty) and ($rest.0 | str starts-with '-') and $rest.0 != '-' {{
+        ^{bin} ...$rest
+    }} else {{
+        let output = (^{bin} -- ...$rest | str trim)
+        if ($output | is-not-empty) {{
+            cd $output
+        }}
+    }}
+}}
+"#),
+            Shell::Powershell => format!(r#"function global:{cmd} {{
+    if ($args.Count -gt 0 -and "$($args[0])" -eq '--') {{
+        $args = @($args | Select-Object -Skip 1)
+    }} elseif ($args.Count -gt 0 -and "$($args[0])" -like '-?*') {{
+        & {bin} @args
+        return
+    }}
+
+    $output = & {bin} -- @args
+    if ($LASTEXITCODE -eq 0 -and $output) {{
+        Set-Location -LiteralPath "$output"
+    }}
+}}
+"#),
+            Shell::Elvish => format!(r#"use str
+fn {cmd} {{|@rest|
+    if (and (> (count $rest) 0) (==s $rest[0] '--')) {{
+        set rest = $rest[1..]
+    }}
+    if (and (> (count $rest) 0) (str:has-prefix $rest[0] '-') (!=s $rest[0] '-')) {{
+        (external {bin}) $@rest
+        return
+    }}
+
+    cd ((external {bin}) -- $@rest)
+}}
+edit:add-var {cmd}~ ${cmd}~
+"#),
+        }
+    }
+
+    // a fresh id on every shell startup, so that `:-` and `:+` follow the directories of this shell only.
+    // the process id alone could be reused by a later shell.
+    fn session(&self) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("export {}=\"$$-$RANDOM\"\n", SESSION),
+            Shell::Fish => format!("set -gx {} \"$fish_pid-\"(random)\n", SESSION),
+            Shell::Nushell => format!("$env.{} = $\"($nu.pid)-(random int)\"\n", SESSION),
+            Shell::Powershell => format!("$env:{} = \"$PID-$(Get-Random)\"\n", SESSION),
+            Shell::Elvish => format!("set E:{} = $pid'-'(randint 0 32768)\n", SESSION),
+        }
+    }
+
+    fn wrap_cd(&self, cmd: &str) -> String {
+        match self {
+            // the options of `cd` itself are left to it, as `cdx` does not know them.
+            Shell::Bash | Shell::Zsh => format!(r#"cd() {{
+    case "${{1:-}}" in
+        -[LPe@]*)
+            builtin cd "$@"
+            return
+            ;;
+    esac
+    {cmd} "$@"
+}}
+"#),
+            Shell::Fish => format!("function cd --wraps cd\n    {cmd} $argv\nend\n"),
+            Shell::Nushell => format!("alias cd = {cmd}\n"),
+            Shell::Powershell => format!("Set-Alias -Name cd -Value {cmd} -Option AllScope -Scope Global -Force\n"),
+            Shell::Elvish => format!("edit:add-var cd~ ${cmd}~\n"),
+        }
+    }
+
+    /// A hook which feeds every directory change of the shell into the history,
+    /// running `bin add` whenever the working directory changes.
+    pub fn hook(&self, bin: &str) -> String {
+        let bin = self.quote(bin);
+        match self {
+            Shell::Bash => format!(r#"__shx_cdx_hook() {{
+    if [ "${{__shx_cdx_pwd:-}}" != "$PWD" ]; then
+        __shx_cdx_pwd="$PWD"
+        {bin} add -- "$PWD"
+    fi
+}}
+case ";${{PROMPT_COMMAND:-}};" in
+    *";__shx_cdx_hook;"*) ;;
+    *) PROMPT_COMMAND="__shx_cdx_hook${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}" ;;
+esac
+"#),
+            Shell::Zsh => format!(r#"__shx_cdx_hook() {{
+    {bin} add -- "$PWD"
+}}
+autoload -Uz add-zsh-hook
+add-zsh-hook chpwd __shx_cdx_hook
+"#),
+            Shell::Fish => format!(r#"function __shx_cdx_hook --on-variable PWD
+    {bin} add -- "$PWD"
+end
+"#),
+            Shell::Nushell => format!(r#"$env.config = (
+    $env.config?
+    | default {{}}
+    | upsert hooks {{ default {{}} }}
+    | upsert hooks.env_change {{ default {{}} }}
+    | upsert hooks.env_change.PWD {{ default [] }}
+)
+$env.config.hooks.env_change.PWD = ($env.config.hooks.env_change.PWD | append {{|_, dir| ^{bin} add -- $dir }})
+"#),
+            Shell::Powershell => format!(r#"$global:__shx_cdx_pwd = $null
+$global:__shx_cdx_prompt = $function:prompt
+function global:prompt {{
+    $dir = (Get-Location -PSProvider FileSystem).ProviderPath
+    if ($dir -ne $global:__shx_cdx_pwd) {{
+        $global:__shx_cdx_pwd = $dir
+        & {bin} add -- $dir
+    }}
+    & $global:__shx_cdx_prompt
+}}
+"#),
+            Shell::Elvish => format!(r#"set after-chdir = [$@after-chdir {{|_| (external {bin}) add -- $pwd }}]
+"#),
+        }
+    }
+
+    /// Quotes `s` as a single word of the shell.
+    fn quote(&self, s: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("'{}'", s.replace('\'', r"'\''")),
+            Shell::Fish => format!("'{}'", s.replace('\\', r"\\").replace('\'', r"\'")),
+            Shell::Nushell => format!("\"{}\"", s.replace('\\', r"\\").replace('"', "\\\"")),
+            Shell::Powershell | Shell::Elvish => format!("'{}'", s.replace('\'', "''")),
+        }
+    }
+}
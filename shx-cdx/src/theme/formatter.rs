@@ -1,11 +1,17 @@
-use std::fmt::{Debug, Display};
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::rc::Rc;
 
 use colored::Colorize;
 
 use crate::history::Entry;
-use crate::theme::color::StyledBridge;
+use crate::matcher::fuzzy::FuzzyMatcher;
+use crate::theme::color::{ColorBridge, StyledBridge};
 use crate::theme::Theme;
 
+/// The filter typed so far, shared by every entry of a selection to highlight what it matches.
+pub type Query = Rc<RefCell<String>>;
+
 pub trait ToPretty {
     fn prettify(&self, index: usize, theme: &Theme) -> StyledEntry;
 }
@@ -17,7 +23,7 @@ impl ToPretty for Entry {
         let canonical = StyledBridge::new(self.canonical.clone(), theme.canonical());
         let raw = StyledBridge::new(self.raw.clone(), theme.raw());
 
-        StyledEntry { index, canonical, raw }
+        StyledEntry { index, canonical, raw, visits: None, note: None, highlight: theme.highlight(), assist: theme.assist(), query: Query::default() }
     }
 }
 
@@ -25,23 +31,70 @@ pub struct StyledEntry {
     index: StyledBridge<usize>,
     raw: StyledBridge<String>,
     canonical: StyledBridge<String>,
+    /// When the directory was last visited, how many times and its score, aligned in columns.
+    visits: Option<StyledBridge<String>>,
+    /// Why the directory cannot be changed to, if so.
+    note: Option<StyledBridge<String>>,
+    highlight: ColorBridge,
+    assist: ColorBridge,
+    query: Query,
 }
 
 impl StyledEntry {
-    pub fn to_entry(&self) -> Entry {
-        Entry {
-            raw: self.raw.content.clone(),
-            canonical: self.canonical.content.clone(),
-        }
+    pub fn canonical(&self) -> &str {
+        &self.canonical.content
+    }
+
+    pub fn with_query(self, query: Query) -> Self {
+        Self { query, ..self }
+    }
+
+    pub fn with_visits(self, last_visit: &str, count: u32, score: f64) -> Self {
+        let visits = format!("{:>10} {:>5} {:>8.2}", last_visit, count, score);
+        let visits = Some(StyledBridge::new(visits, self.assist));
+        Self { visits, ..self }
+    }
+
+    pub fn with_note(self, note: Option<&str>) -> Self {
+        let note = note.map(|it| StyledBridge::new(it.to_string(), self.highlight));
+        Self { note, ..self }
+    }
+
+    // colors the characters matched by the query, if any, with the highlight color.
+    fn highlight_canonical(&self) -> String {
+        let query = self.query.borrow();
+        let indices = match query.is_empty() {
+            true => None,
+            false => FuzzyMatcher::new(&query).indices(self.canonical()),
+        };
+        let Some(indices) = indices else {
+            return self.canonical.colorize().to_string();
+        };
+
+        self.canonical().chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let color = if indices.contains(&i) { self.highlight } else { self.canonical.style };
+                c.to_string().color(color.to_colored()).to_string()
+            })
+            .collect()
     }
 }
 
 impl Display for StyledEntry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let index = self.index.colorize();
-        let canonical = self.canonical.colorize();
+        let canonical = self.highlight_canonical();
         let raw = self.raw.colorize_with(|it| format!("<{}>", it));
 
-        write!(f, "{index}: {canonical} {raw}")
+        write!(f, "{index}: ")?;
+        if let Some(visits) = &self.visits {
+            write!(f, "{}  ", visits.colorize())?;
+        }
+        write!(f, "{canonical} {raw}")?;
+        match &self.note {
+            Some(note) => write!(f, " {}", note.colorize_with(|it| format!("({})", it))),
+            None => Ok(()),
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,3 @@
-use std::fmt::Display;
-
 use inquire::ui::{Attributes, RenderConfig, Styled, StyleSheet};
 
 use color::ColorBridge;
@@ -15,64 +13,58 @@ pub struct Theme {
     raw: ColorBridge,
     selected: ColorBridge,
     assist: ColorBridge,
+    highlight: ColorBridge,
 }
 
 impl Theme {
-    pub fn prompt(&self) -> ColorBridge {
-        self.prompt.clone()
-    }
-
     pub fn index(&self) -> ColorBridge {
-        self.index.clone()
+        self.index
     }
 
     pub fn canonical(&self) -> ColorBridge {
-        self.canonical.clone()
+        self.canonical
     }
 
     pub fn raw(&self) -> ColorBridge {
-        self.raw.clone()
+        self.raw
     }
 
-    pub fn selected(&self) -> ColorBridge {
-        self.selected.clone()
+    pub fn assist(&self) -> ColorBridge {
+        self.assist
     }
 
-    pub fn assist(&self) -> ColorBridge {
-        self.assist.clone()
+    pub fn highlight(&self) -> ColorBridge {
+        self.highlight
     }
 }
 
 pub trait SelectTheme {
-    fn render_config(&self) -> RenderConfig;
+    fn render_config(&self) -> RenderConfig<'_>;
     fn prompt_stylesheet(&self) -> StyleSheet;
     fn user_input_stylesheet(&self) -> StyleSheet;
-    fn index_stylesheet(&self) -> StyleSheet;
-    fn canonical_stylesheet(&self) -> StyleSheet;
-    fn raw_stylesheet(&self) -> StyleSheet;
     fn selected_stylesheet(&self) -> StyleSheet;
     fn assist_stylesheet(&self) -> StyleSheet;
 }
 
 
 impl SelectTheme for Theme {
-    fn render_config(&self) -> RenderConfig {
-        let mut render_config = RenderConfig::default();
-
-        // prompt
-        render_config.prompt_prefix = Styled::new("$").with_style_sheet(self.prompt_stylesheet());
-        render_config.prompt = self.prompt_stylesheet();
-
-        // user input
-        render_config.answer = self.user_input_stylesheet().with_attr(Attributes::BOLD);
-        render_config.text_input = self.user_input_stylesheet();
-
-        // options(a.k.a. selections) will be styled by itself
-        render_config.highlighted_option_prefix = Styled::new(">").with_style_sheet(self.selected_stylesheet());
-
-        // helps
-        render_config.help_message = self.assist_stylesheet();
-        render_config
+    fn render_config(&self) -> RenderConfig<'_> {
+        RenderConfig {
+            // prompt
+            prompt_prefix: Styled::new("$").with_style_sheet(self.prompt_stylesheet()),
+            prompt: self.prompt_stylesheet(),
+
+            // user input
+            answer: self.user_input_stylesheet().with_attr(Attributes::BOLD),
+            text_input: self.user_input_stylesheet(),
+
+            // options(a.k.a. selections) will be styled by itself
+            highlighted_option_prefix: Styled::new(">").with_style_sheet(self.selected_stylesheet()),
+
+            // helps
+            help_message: self.assist_stylesheet(),
+            ..RenderConfig::default()
+        }
     }
 
     fn prompt_stylesheet(&self) -> StyleSheet {
@@ -83,18 +75,6 @@ impl SelectTheme for Theme {
         self.user_input.to_stylesheet()
     }
 
-    fn index_stylesheet(&self) -> StyleSheet {
-        self.index.to_stylesheet()
-    }
-
-    fn canonical_stylesheet(&self) -> StyleSheet {
-        self.canonical.to_stylesheet()
-    }
-
-    fn raw_stylesheet(&self) -> StyleSheet {
-        self.raw.to_stylesheet()
-    }
-
     fn selected_stylesheet(&self) -> StyleSheet {
         self.selected.to_stylesheet()
     }
@@ -114,6 +94,7 @@ impl Default for Theme {
             raw: ColorBridge::LightBlue,
             assist: ColorBridge::LightYellow,
             selected: ColorBridge::LightGreen,
+            highlight: ColorBridge::LightRed,
         }
     }
 }
\ No newline at end of file
@@ -4,6 +4,54 @@ use serde::{Deserialize, Serialize};
 pub struct CdxConfig {
     search_size: Option<usize>,
     max_size: Option<usize>,
+    /// Hours after which the recency weight of an entry is halved.
+    frecency_half_life: Option<u64>,
+    /// Total visit count above which every count is aged down.
+    aging_threshold: Option<u32>,
+    /// How deep `--learn` descends below the given directory.
+    learn_depth: Option<usize>,
+    /// Directory names `--learn` never descends into, on top of ignore files.
+    learn_ignore: Option<Vec<String>>,
+    /// Files or directories whose presence marks the root of a project.
+    project_markers: Option<Vec<String>>,
+    /// How many times the score of a directory within the current project is multiplied by.
+    project_boost: Option<f64>,
+    /// What a shortcut matching several directories does.
+    ambiguity: Option<Ambiguity>,
+    /// Whether to change to the path a directory was reached by, or to the one its symlinks resolve to.
+    path_mode: Option<PathMode>,
+    /// Days `--prune` keeps directories which seem to be on an unmounted volume, since their last visit.
+    prune_grace_days: Option<u64>,
+    /// Milliseconds to wait for a directory to answer, before taking its filesystem as unavailable.
+    stat_timeout_ms: Option<u64>,
+    /// How deep a shortcut without a match in history searches below each directory it searches.
+    search_depth: Option<usize>,
+    /// Milliseconds a shortcut without a match in history searches the filesystem for.
+    search_timeout_ms: Option<u64>,
+    /// Directories a shortcut without a match in history searches, after the current one.
+    search_roots: Option<Vec<String>>,
+}
+
+/// What a shortcut matching several directories does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ambiguity {
+    /// Jumps to the best-ranked directory.
+    Best,
+    /// Picks one of the candidates interactively.
+    Prompt,
+    /// Fails, listing the candidates.
+    Fail,
+}
+
+/// Which path of a directory to change to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathMode {
+    /// The path with its symlinks unresolved, like `cd -L`.
+    Logical,
+    /// The path with its symlinks resolved, like `cd -P`.
+    Physical,
 }
 
 impl CdxConfig {
@@ -14,6 +62,58 @@ impl CdxConfig {
     pub fn max_size(&self) -> usize {
         self.max_size.unwrap_or(1024)
     }
+
+    pub fn frecency_half_life(&self) -> u64 {
+        self.frecency_half_life.unwrap_or(168)
+    }
+
+    pub fn aging_threshold(&self) -> u32 {
+        self.aging_threshold.unwrap_or(10000)
+    }
+
+    pub fn learn_depth(&self) -> usize {
+        self.learn_depth.unwrap_or(4)
+    }
+
+    pub fn learn_ignore(&self) -> Vec<String> {
+        self.learn_ignore.clone().unwrap_or_else(default_learn_ignore)
+    }
+
+    pub fn project_markers(&self) -> Vec<String> {
+        self.project_markers.clone().unwrap_or_else(default_project_markers)
+    }
+
+    pub fn project_boost(&self) -> f64 {
+        self.project_boost.unwrap_or(4.0)
+    }
+
+    pub fn ambiguity(&self) -> Ambiguity {
+        self.ambiguity.unwrap_or(Ambiguity::Best)
+    }
+
+    pub fn path_mode(&self) -> PathMode {
+        self.path_mode.unwrap_or(PathMode::Logical)
+    }
+
+    pub fn prune_grace_days(&self) -> u64 {
+        self.prune_grace_days.unwrap_or(30)
+    }
+
+    pub fn stat_timeout_ms(&self) -> u64 {
+        self.stat_timeout_ms.unwrap_or(500)
+    }
+
+    pub fn search_depth(&self) -> usize {
+        self.search_depth.unwrap_or(3)
+    }
+
+    pub fn search_timeout_ms(&self) -> u64 {
+        self.search_timeout_ms.unwrap_or(200)
+    }
+
+    pub fn search_roots(&self) -> Vec<String> {
+        self.search_roots.clone().unwrap_or_default()
+    }
 }
 
 impl Default for CdxConfig {
@@ -21,6 +121,27 @@ impl Default for CdxConfig {
         CdxConfig {
             search_size: Some(30),
             max_size: Some(1024),
+            frecency_half_life: Some(168),
+            aging_threshold: Some(10000),
+            learn_depth: Some(4),
+            learn_ignore: Some(default_learn_ignore()),
+            project_markers: Some(default_project_markers()),
+            project_boost: Some(4.0),
+            ambiguity: Some(Ambiguity::Best),
+            path_mode: Some(PathMode::Logical),
+            prune_grace_days: Some(30),
+            stat_timeout_ms: Some(500),
+            search_depth: Some(3),
+            search_timeout_ms: Some(200),
+            search_roots: Some(Vec::new()),
         }
     }
 }
+
+fn default_learn_ignore() -> Vec<String> {
+    vec!["node_modules".to_string(), "target".to_string()]
+}
+
+fn default_project_markers() -> Vec<String> {
+    vec![".git".to_string(), "Cargo.toml".to_string(), "package.json".to_string()]
+}
@@ -6,20 +6,12 @@ use serde::{Deserialize, Serialize};
 
 use crate::cdx::CdxConfig;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub cdx_config: CdxConfig,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Config {
-            cdx_config: CdxConfig::default(),
-        }
-    }
-}
-
 pub fn config() -> anyhow::Result<Config> {
     let home = shx_home()?;
     let config_path = home.join("config.toml");
@@ -66,7 +58,7 @@ fn shx_home() -> anyhow::Result<PathBuf> {
         return Ok(PathBuf::from(shx_home));
     }
     let home = home()?;
-    let shx_home = PathBuf::from(home).join(".shx");
+    let shx_home = home.join(".shx");
     // ensure shx_home exists
     if !shx_home.exists() {
         fs::create_dir_all(&shx_home)